@@ -0,0 +1,484 @@
+//! A builder for [W3C action chains].
+//!
+//! [W3C action chains]: https://www.w3.org/TR/webdriver/#actions
+
+use serde_json::{self, Value};
+use std::time::Duration;
+use {element_ref, Element};
+
+/// The kind of device a pointer input source represents.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum PointerType {
+    /// A mouse.
+    Mouse,
+    /// A pen or stylus.
+    Pen,
+    /// A finger on a touch screen.
+    Touch,
+}
+
+impl PointerType {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            PointerType::Mouse => "mouse",
+            PointerType::Pen => "pen",
+            PointerType::Touch => "touch",
+        }
+    }
+}
+
+/// A button on a pointer device.
+///
+/// Pens and touch pointers only have `Left`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum MouseButton {
+    /// The primary button.
+    Left,
+    /// The auxiliary button, usually the wheel.
+    Middle,
+    /// The secondary button.
+    Right,
+    /// The "back" button found on some mice.
+    Back,
+    /// The "forward" button found on some mice.
+    Forward,
+}
+
+impl MouseButton {
+    fn code(&self) -> u64 {
+        match *self {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::Back => 3,
+            MouseButton::Forward => 4,
+        }
+    }
+}
+
+/// The characters WebDriver uses to represent keys that do not produce text.
+///
+/// These can be passed to `Actions::key_down` and `Actions::key_up`, or be included in the text
+/// given to `Actions::send_keys`. See the [spec] for the full list.
+///
+/// [spec]: https://www.w3.org/TR/webdriver/#keyboard-actions
+#[derive(Clone, Copy, Debug)]
+pub struct Key;
+
+#[allow(missing_docs)]
+impl Key {
+    pub const NULL: char = '\u{E000}';
+    pub const CANCEL: char = '\u{E001}';
+    pub const HELP: char = '\u{E002}';
+    pub const BACKSPACE: char = '\u{E003}';
+    pub const TAB: char = '\u{E004}';
+    pub const CLEAR: char = '\u{E005}';
+    pub const RETURN: char = '\u{E006}';
+    pub const ENTER: char = '\u{E007}';
+    pub const SHIFT: char = '\u{E008}';
+    pub const CONTROL: char = '\u{E009}';
+    pub const ALT: char = '\u{E00A}';
+    pub const PAUSE: char = '\u{E00B}';
+    pub const ESCAPE: char = '\u{E00C}';
+    pub const SPACE: char = '\u{E00D}';
+    pub const PAGE_UP: char = '\u{E00E}';
+    pub const PAGE_DOWN: char = '\u{E00F}';
+    pub const END: char = '\u{E010}';
+    pub const HOME: char = '\u{E011}';
+    pub const LEFT: char = '\u{E012}';
+    pub const UP: char = '\u{E013}';
+    pub const RIGHT: char = '\u{E014}';
+    pub const DOWN: char = '\u{E015}';
+    pub const INSERT: char = '\u{E016}';
+    pub const DELETE: char = '\u{E017}';
+    pub const F1: char = '\u{E031}';
+    pub const F2: char = '\u{E032}';
+    pub const F3: char = '\u{E033}';
+    pub const F4: char = '\u{E034}';
+    pub const F5: char = '\u{E035}';
+    pub const F6: char = '\u{E036}';
+    pub const F7: char = '\u{E037}';
+    pub const F8: char = '\u{E038}';
+    pub const F9: char = '\u{E039}';
+    pub const F10: char = '\u{E03A}';
+    pub const F11: char = '\u{E03B}';
+    pub const F12: char = '\u{E03C}';
+    pub const META: char = '\u{E03D}';
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum SourceKind {
+    Null,
+    Key,
+    Pointer(PointerType),
+    Wheel,
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    id: String,
+    kind: SourceKind,
+    actions: Vec<Value>,
+}
+
+/// A chain of input actions, to be performed with `Client::perform_actions`.
+///
+/// Each method adds one action to the end of the chain, and actions are performed in order.
+/// Under the hood, every action is placed in its own [tick], and all other input sources pause
+/// during that tick.
+///
+/// For example, to shift-click two items in a list:
+///
+/// ```no_run
+/// # extern crate tokio_core;
+/// # extern crate futures;
+/// # extern crate fantoccini;
+/// # fn main() {
+/// use fantoccini::{Actions, Client, Key, Locator};
+/// use futures::future::Future;
+/// let mut core = tokio_core::reactor::Core::new().unwrap();
+/// let c = core.run(Client::new("http://localhost:4444", &core.handle())).unwrap();
+/// let f = c.find(Locator::Css("li:first-child")).join(c.find(Locator::Css("li:last-child")))
+///     .and_then(|(first, last)| {
+///         let actions = Actions::new()
+///             .move_to(&first, 0, 0)
+///             .click()
+///             .key_down(Key::SHIFT)
+///             .move_to(&last, 0, 0)
+///             .click()
+///             .key_up(Key::SHIFT);
+///         c.perform_actions(actions)
+///     });
+/// core.run(f).unwrap();
+/// # }
+/// ```
+///
+/// [tick]: https://www.w3.org/TR/webdriver/#dfn-tick
+#[derive(Clone, Debug)]
+pub struct Actions {
+    sources: Vec<Source>,
+    pointer: Option<usize>,
+    duration: u64,
+}
+
+impl Default for Actions {
+    fn default() -> Self {
+        Actions::new()
+    }
+}
+
+impl Actions {
+    /// Start a new, empty action chain.
+    pub fn new() -> Self {
+        Actions {
+            sources: Vec::new(),
+            pointer: None,
+            duration: 0,
+        }
+    }
+
+    /// Use the pointer input source with the given `id` for subsequent pointer actions.
+    ///
+    /// The source is created if it does not exist yet. Until this is called, pointer actions use
+    /// a mouse with the id `mouse`. Using several touch pointers allows multi-touch gestures.
+    pub fn pointer(mut self, id: &str, kind: PointerType) -> Self {
+        self.pointer = Some(self.source(id, SourceKind::Pointer(kind)));
+        self
+    }
+
+    /// Set how long subsequent pointer moves and scrolls take.
+    ///
+    /// By default, they are instantaneous.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = millis(duration);
+        self
+    }
+
+    /// Pause all input sources for the given `duration`.
+    pub fn pause(mut self, duration: Duration) -> Self {
+        if self.sources.is_empty() {
+            self.source("pause", SourceKind::Null);
+        }
+
+        let pause = json!({ "type": "pause", "duration": millis(duration) });
+        for s in &mut self.sources {
+            s.actions.push(pause.clone());
+        }
+        self
+    }
+
+    /// Move the pointer to the given offset from the center of `element`.
+    pub fn move_to(self, element: &Element, x: i64, y: i64) -> Self {
+        let origin = element_ref(&element.e);
+        self.pointer_move(origin, x, y)
+    }
+
+    /// Move the pointer to the given coordinates, relative to the top-left corner of the
+    /// viewport.
+    pub fn move_to_viewport(self, x: i64, y: i64) -> Self {
+        self.pointer_move(Value::from("viewport"), x, y)
+    }
+
+    /// Move the pointer by the given offset from its current position.
+    pub fn move_by(self, x: i64, y: i64) -> Self {
+        self.pointer_move(Value::from("pointer"), x, y)
+    }
+
+    /// Press the given pointer `button`.
+    pub fn press(self, button: MouseButton) -> Self {
+        let action = json!({ "type": "pointerDown", "button": button.code() });
+        self.pointer_action(action)
+    }
+
+    /// Release the given pointer `button`.
+    pub fn release(self, button: MouseButton) -> Self {
+        let action = json!({ "type": "pointerUp", "button": button.code() });
+        self.pointer_action(action)
+    }
+
+    /// Click the left button at the pointer's current position.
+    pub fn click(self) -> Self {
+        self.press(MouseButton::Left).release(MouseButton::Left)
+    }
+
+    /// Double-click the left button at the pointer's current position.
+    pub fn double_click(self) -> Self {
+        self.click().click()
+    }
+
+    /// Click the right button at the pointer's current position.
+    pub fn context_click(self) -> Self {
+        self.press(MouseButton::Right).release(MouseButton::Right)
+    }
+
+    /// Press the left button on `from`, move to `to`, and release the button there.
+    pub fn drag_and_drop(self, from: &Element, to: &Element) -> Self {
+        self.move_to(from, 0, 0)
+            .press(MouseButton::Left)
+            .move_to(to, 0, 0)
+            .release(MouseButton::Left)
+    }
+
+    /// Press the given key.
+    ///
+    /// Modifier keys, such as `Key::SHIFT`, stay pressed until they are released with `key_up`,
+    /// and affect all pointer actions in between.
+    pub fn key_down(self, key: char) -> Self {
+        let action = json!({ "type": "keyDown", "value": key.to_string() });
+        self.key_action(action)
+    }
+
+    /// Release the given key.
+    pub fn key_up(self, key: char) -> Self {
+        let action = json!({ "type": "keyUp", "value": key.to_string() });
+        self.key_action(action)
+    }
+
+    /// Press and release each character of `text` in turn.
+    pub fn send_keys(self, text: &str) -> Self {
+        text.chars().fold(self, |a, c| a.key_down(c).key_up(c))
+    }
+
+    /// Scroll by the given amount, with the scroll wheel positioned at the given offset from the
+    /// center of `element`.
+    pub fn scroll_from(self, element: &Element, x: i64, y: i64, dx: i64, dy: i64) -> Self {
+        let origin = element_ref(&element.e);
+        self.scroll(origin, x, y, dx, dy)
+    }
+
+    /// Scroll by the given amount, with the scroll wheel positioned at the given coordinates,
+    /// relative to the top-left corner of the viewport.
+    pub fn scroll_from_viewport(self, x: i64, y: i64, dx: i64, dy: i64) -> Self {
+        self.scroll(Value::from("viewport"), x, y, dx, dy)
+    }
+
+    /// Serialize this chain as the parameters of the [perform actions] command.
+    ///
+    /// [perform actions]: https://www.w3.org/TR/webdriver/#perform-actions
+    pub(crate) fn to_json(&self) -> Value {
+        let sources: Vec<_> = self
+            .sources
+            .iter()
+            .map(|s| {
+                let mut source = serde_json::Map::new();
+                let kind = match s.kind {
+                    SourceKind::Null => "none",
+                    SourceKind::Key => "key",
+                    SourceKind::Pointer(_) => "pointer",
+                    SourceKind::Wheel => "wheel",
+                };
+                source.insert("type".to_string(), Value::from(kind));
+                source.insert("id".to_string(), Value::from(&*s.id));
+                if let SourceKind::Pointer(pt) = s.kind {
+                    source.insert(
+                        "parameters".to_string(),
+                        json!({ "pointerType": pt.as_str() }),
+                    );
+                }
+                source.insert("actions".to_string(), Value::Array(s.actions.clone()));
+                Value::Object(source)
+            })
+            .collect();
+        json!({ "actions": sources })
+    }
+
+    /// Find the input source with the given `id`, creating it if it does not exist.
+    ///
+    /// A new source pauses for every tick that has already been added to the chain.
+    fn source(&mut self, id: &str, kind: SourceKind) -> usize {
+        if let Some(i) = self.sources.iter().position(|s| s.id == id) {
+            return i;
+        }
+
+        let ticks = self.sources.first().map(|s| s.actions.len()).unwrap_or(0);
+        self.sources.push(Source {
+            id: id.to_string(),
+            kind,
+            actions: vec![json!({ "type": "pause" }); ticks],
+        });
+        self.sources.len() - 1
+    }
+
+    /// Add `action` to the input source at index `source` in a new tick.
+    fn push(mut self, source: usize, action: Value) -> Self {
+        for (i, s) in self.sources.iter_mut().enumerate() {
+            if i == source {
+                s.actions.push(action.clone());
+            } else {
+                s.actions.push(json!({ "type": "pause" }));
+            }
+        }
+        self
+    }
+
+    fn pointer_action(mut self, action: Value) -> Self {
+        let pointer = match self.pointer {
+            Some(pointer) => pointer,
+            None => {
+                let pointer = self.source("mouse", SourceKind::Pointer(PointerType::Mouse));
+                self.pointer = Some(pointer);
+                pointer
+            }
+        };
+        self.push(pointer, action)
+    }
+
+    fn pointer_move(self, origin: Value, x: i64, y: i64) -> Self {
+        let action = json!({
+            "type": "pointerMove",
+            "duration": self.duration,
+            "origin": origin,
+            "x": x,
+            "y": y,
+        });
+        self.pointer_action(action)
+    }
+
+    fn key_action(mut self, action: Value) -> Self {
+        let key = self.source("keyboard", SourceKind::Key);
+        self.push(key, action)
+    }
+
+    fn scroll(mut self, origin: Value, x: i64, y: i64, dx: i64, dy: i64) -> Self {
+        let action = json!({
+            "type": "scroll",
+            "duration": self.duration,
+            "origin": origin,
+            "x": x,
+            "y": y,
+            "deltaX": dx,
+            "deltaY": dy,
+        });
+        let wheel = self.source("wheel", SourceKind::Wheel);
+        self.push(wheel, action)
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + u64::from(d.subsec_nanos() / 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_stay_in_sync() {
+        let a = Actions::new()
+            .move_to_viewport(10, 20)
+            .key_down(Key::SHIFT)
+            .click()
+            .key_up(Key::SHIFT)
+            .pause(Duration::from_millis(1500));
+
+        let json = a.to_json();
+        let sources = json["actions"].as_array().unwrap();
+        assert_eq!(sources.len(), 2);
+
+        let mouse = &sources[0];
+        assert_eq!(mouse["type"], "pointer");
+        assert_eq!(mouse["id"], "mouse");
+        assert_eq!(mouse["parameters"]["pointerType"], "mouse");
+        let keys = &sources[1];
+        assert_eq!(keys["type"], "key");
+        assert!(keys.get("parameters").is_none());
+
+        let types = |s: &Value| -> Vec<String> {
+            s["actions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|a| a["type"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            types(mouse),
+            vec![
+                "pointerMove",
+                "pause",
+                "pointerDown",
+                "pointerUp",
+                "pause",
+                "pause"
+            ]
+        );
+        assert_eq!(
+            types(keys),
+            vec!["pause", "keyDown", "pause", "pause", "keyUp", "pause"]
+        );
+
+        assert_eq!(mouse["actions"][0]["origin"], "viewport");
+        assert_eq!(mouse["actions"][0]["x"], 10);
+        assert_eq!(keys["actions"][1]["value"], "\u{E008}");
+        assert_eq!(keys["actions"][5]["duration"], 1500);
+    }
+
+    #[test]
+    fn multiple_pointers_and_wheel() {
+        let a = Actions::new()
+            .pointer("finger1", PointerType::Touch)
+            .press(MouseButton::Left)
+            .pointer("finger2", PointerType::Touch)
+            .press(MouseButton::Left)
+            .duration(Duration::from_millis(250))
+            .scroll_from_viewport(0, 0, 0, 100);
+
+        let json = a.to_json();
+        let sources = json["actions"].as_array().unwrap();
+        assert_eq!(sources.len(), 3);
+        assert_eq!(sources[0]["parameters"]["pointerType"], "touch");
+        // the second finger was added after the first tick
+        assert_eq!(sources[1]["actions"][0]["type"], "pause");
+        assert_eq!(sources[1]["actions"][1]["type"], "pointerDown");
+        assert_eq!(sources[2]["type"], "wheel");
+        assert_eq!(sources[2]["actions"][2]["deltaY"], 100);
+        assert_eq!(sources[2]["actions"][2]["duration"], 250);
+    }
+
+    #[test]
+    fn pause_without_sources() {
+        let json = Actions::new().pause(Duration::from_secs(1)).to_json();
+        assert_eq!(json["actions"][0]["type"], "none");
+        assert_eq!(json["actions"][0]["actions"][0]["duration"], 1000);
+    }
+}
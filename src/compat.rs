@@ -0,0 +1,98 @@
+//! Compatibility with the `rustc_serialize` JSON types used by earlier versions of this crate.
+//!
+//! All JSON that passes through the public API of this crate is now a `serde_json::Value`. The
+//! conversions in this module let code that still uses `rustc_serialize::json::Json` keep working
+//! while it is being migrated.
+
+use error;
+use futures::Future;
+use rustc_serialize::json::{Json, ToJson};
+use serde_json::{self, Value};
+use {Client, Element};
+
+/// Convert a `serde_json` value into the equivalent `rustc_serialize` value.
+pub fn to_rustc(v: &Value) -> Json {
+    match *v {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Boolean(b),
+        Value::Number(ref n) => {
+            if let Some(n) = n.as_u64() {
+                Json::U64(n)
+            } else if let Some(n) = n.as_i64() {
+                Json::I64(n)
+            } else {
+                Json::F64(n.as_f64().unwrap())
+            }
+        }
+        Value::String(ref s) => Json::String(s.clone()),
+        Value::Array(ref vs) => Json::Array(vs.iter().map(to_rustc).collect()),
+        Value::Object(ref o) => {
+            Json::Object(o.iter().map(|(k, v)| (k.clone(), to_rustc(v))).collect())
+        }
+    }
+}
+
+/// Convert a `rustc_serialize` value into the equivalent `serde_json` value.
+///
+/// Since JSON cannot represent them, non-finite numbers become `null`.
+pub fn from_rustc(v: &Json) -> Value {
+    match *v {
+        Json::Null => Value::Null,
+        Json::Boolean(b) => Value::Bool(b),
+        Json::I64(n) => Value::from(n),
+        Json::U64(n) => Value::from(n),
+        Json::F64(n) => serde_json::Number::from_f64(n)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        Json::String(ref s) => Value::String(s.clone()),
+        Json::Array(ref vs) => Value::Array(vs.iter().map(from_rustc).collect()),
+        Json::Object(ref o) => {
+            Value::Object(o.iter().map(|(k, v)| (k.clone(), from_rustc(v))).collect())
+        }
+    }
+}
+
+impl Client {
+    /// Execute the given JavaScript `script` like `execute`, but with `rustc_serialize` arguments
+    /// and result.
+    #[deprecated(note = "use `Client::execute`, which takes and returns `serde_json::Value`")]
+    pub fn execute_rustc(
+        &self,
+        script: &str,
+        args: Vec<Json>,
+    ) -> impl Future<Item = Json, Error = error::CmdError> + 'static {
+        let args = args.iter().map(from_rustc).collect();
+        self.execute(script, args).map(|v| to_rustc(&v))
+    }
+}
+
+impl ToJson for Element {
+    fn to_json(&self) -> Json {
+        self.e.to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let v = json!({
+            "null": null,
+            "bool": true,
+            "neg": -1,
+            "pos": 1,
+            "float": 0.5,
+            "array": ["a", 2],
+            "object": { "nested": [] },
+        });
+        let r = to_rustc(&v);
+        assert_eq!(r.find("neg"), Some(&Json::I64(-1)));
+        assert_eq!(r.find("pos"), Some(&Json::U64(1)));
+        assert_eq!(r.find("float"), Some(&Json::F64(0.5)));
+        assert_eq!(from_rustc(&r), v);
+
+        assert_eq!(from_rustc(&Json::F64(::std::f64::NAN)), Value::Null);
+    }
+}
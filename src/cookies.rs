@@ -0,0 +1,553 @@
+use hyper;
+use serde_json::{self, Value};
+use url;
+
+/// The [`SameSite`] policy of a cookie.
+///
+/// [`SameSite`]: https://tools.ietf.org/html/draft-ietf-httpbis-cookie-same-site-00
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum SameSite {
+    /// The cookie is only sent for same-site requests.
+    Strict,
+    /// The cookie is also sent for top-level cross-site navigations.
+    Lax,
+    /// The cookie is sent with all requests.
+    None,
+}
+
+impl SameSite {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match &*s.to_ascii_lowercase() {
+            "strict" => Some(SameSite::Strict),
+            "lax" => Some(SameSite::Lax),
+            "none" => Some(SameSite::None),
+            _ => None,
+        }
+    }
+}
+
+/// A cookie as seen by the browser.
+///
+/// See the [WebDriver cookie API] for the meaning of each field.
+///
+/// [WebDriver cookie API]: https://www.w3.org/TR/webdriver/#cookies
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Cookie {
+    /// The name of the cookie.
+    pub name: String,
+    /// The value of the cookie.
+    pub value: String,
+    /// The path the cookie is restricted to, if any.
+    pub path: Option<String>,
+    /// The domain the cookie is visible to, if any.
+    pub domain: Option<String>,
+    /// Whether the cookie is only sent over secure connections.
+    pub secure: bool,
+    /// Whether the cookie is hidden from JavaScript.
+    pub http_only: bool,
+    /// When the cookie expires, in seconds since the Unix Epoch.
+    ///
+    /// `None` indicates a session cookie.
+    pub expiry: Option<u64>,
+    /// The `SameSite` policy of the cookie, if the driver reported one.
+    pub same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Construct a new session cookie with the given `name` and `value`.
+    ///
+    /// All other fields are left at their defaults, which means the browser will scope the cookie
+    /// to the current page when it is added with `Client::add_cookie`.
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, value: V) -> Self {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            secure: false,
+            http_only: false,
+            expiry: None,
+            same_site: None,
+        }
+    }
+
+    /// Parse a cookie from the serialization described in the [spec].
+    ///
+    /// `None` is returned if the given value is not a conforming cookie.
+    ///
+    /// [spec]: https://www.w3.org/TR/webdriver/#dfn-serialized-cookie
+    pub(crate) fn from_json(cookie: &Value) -> Option<Self> {
+        let cookie = cookie.as_object()?;
+
+        let name = cookie.get("name")?.as_str()?.to_string();
+        let value = cookie.get("value")?.as_str()?.to_string();
+
+        let mut c = Cookie::new(name, value);
+        for (key, v) in cookie {
+            if v.is_null() {
+                continue;
+            }
+
+            match &**key {
+                "path" => c.path = Some(v.as_str()?.to_string()),
+                "domain" => c.domain = Some(v.as_str()?.to_string()),
+                "secure" => c.secure = v.as_bool()?,
+                "httpOnly" => c.http_only = v.as_bool()?,
+                // some drivers give fractional timestamps
+                "expiry" => {
+                    c.expiry = Some(v.as_u64().or_else(|| v.as_f64().map(|e| e as u64))?);
+                }
+                // Chrome sometimes reports policies such as "no_restriction" or "unspecified"
+                "sameSite" => c.same_site = v.as_str().and_then(SameSite::from_str),
+                _ => {}
+            }
+        }
+
+        Some(c)
+    }
+
+    /// Parse a cookie as returned by the DevTools protocol's [`Network.getAllCookies`].
+    ///
+    /// Like WebDriver, the protocol gives cookies that were set with a `Domain` attribute a
+    /// domain with a leading `.`.
+    ///
+    /// [`Network.getAllCookies`]: https://chromedevtools.github.io/devtools-protocol/tot/Network#method-getAllCookies
+    pub(crate) fn from_cdp(cookie: &Value) -> Option<Self> {
+        let mut c = Cookie::new(
+            cookie.get("name")?.as_str()?,
+            cookie.get("value")?.as_str()?,
+        );
+        c.domain = Some(cookie.get("domain")?.as_str()?.to_string());
+        c.path = Some(cookie.get("path")?.as_str()?.to_string());
+        c.secure = cookie.get("secure")?.as_bool()?;
+        c.http_only = cookie.get("httpOnly")?.as_bool()?;
+        let session = cookie.get("session").and_then(Value::as_bool);
+        c.expiry = match cookie.get("expires").and_then(Value::as_f64) {
+            Some(e) if e >= 0.0 && session != Some(true) => Some(e as u64),
+            _ => None,
+        };
+        c.same_site = cookie
+            .get("sameSite")
+            .and_then(Value::as_str)
+            .and_then(SameSite::from_str);
+        Some(c)
+    }
+
+    /// Determine whether this cookie should be sent along with a request for `url`.
+    ///
+    /// This follows the rules from [RFC 6265 section 5.4], with `origin` being the URL of the
+    /// document the cookie was retrieved from, and `now` the current time in seconds since the
+    /// Unix Epoch.
+    ///
+    /// WebDriver does not tell us whether a cookie is host-only. Browsers report cookies that were
+    /// set with a `Domain` attribute with a leading `.`, so we treat cookies whose domain does not
+    /// start with a `.` as host-only.
+    ///
+    /// [RFC 6265 section 5.4]: https://tools.ietf.org/html/rfc6265#section-5.4
+    pub(crate) fn matches(&self, url: &url::Url, origin: &url::Url, now: u64) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+
+        // https://tools.ietf.org/html/rfc6265#section-5.1.3
+        let domain_ok = match self.domain {
+            Some(ref domain) if domain.starts_with('.') => {
+                let domain = domain[1..].to_ascii_lowercase();
+                let is_name = match url.host() {
+                    Some(url::Host::Domain(_)) => true,
+                    _ => false,
+                };
+                host == domain || (is_name && host.ends_with(&format!(".{}", domain)))
+            }
+            Some(ref domain) => host == domain.to_ascii_lowercase(),
+            None => origin
+                .host_str()
+                .map(|origin| host == origin.to_ascii_lowercase())
+                .unwrap_or(false),
+        };
+        if !domain_ok {
+            return false;
+        }
+
+        // https://tools.ietf.org/html/rfc6265#section-5.1.4
+        let path = self.path.as_ref().map(|p| &**p).unwrap_or("/");
+        let request_path = url.path();
+        let path_ok = request_path == path
+            || (request_path.starts_with(path)
+                && (path.ends_with('/') || request_path[path.len()..].starts_with('/')));
+        if !path_ok {
+            return false;
+        }
+
+        if self.secure && url.scheme() != "https" && url.scheme() != "wss" {
+            return false;
+        }
+
+        match self.expiry {
+            Some(expiry) => expiry > now,
+            None => true,
+        }
+    }
+}
+
+/// The current time in seconds since the Unix Epoch.
+pub(crate) fn now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Build the `Cookie` header to send with a request for `url` from the given browser cookies.
+///
+/// `origin` is the URL of the document the cookies were retrieved from. Cookies are filtered
+/// according to `Cookie::matches`, and ordered with longer paths first as recommended by [RFC
+/// 6265 section 5.4].
+///
+/// [RFC 6265 section 5.4]: https://tools.ietf.org/html/rfc6265#section-5.4
+pub(crate) fn jar_for(
+    cookies: &[Cookie],
+    url: &url::Url,
+    origin: &url::Url,
+) -> hyper::header::Cookie {
+    let now = now();
+    let mut cookies: Vec<_> = cookies
+        .iter()
+        .filter(|c| c.matches(url, origin, now))
+        .collect();
+    cookies.sort_by(|a, b| {
+        let a = a.path.as_ref().map(|p| p.len()).unwrap_or(1);
+        let b = b.path.as_ref().map(|p| p.len()).unwrap_or(1);
+        b.cmp(&a)
+    });
+
+    let mut jar = hyper::header::Cookie::new();
+    for cookie in cookies {
+        jar.append(cookie.name.clone(), cookie.value.clone());
+    }
+    jar
+}
+
+/// Parse a `Set-Cookie` header received in response to a request for `url`.
+///
+/// The returned cookie has its domain and path filled in following [RFC 6265 section 5.3], so
+/// that it can be matched using `Cookie::matches`. `None` is returned if the header is malformed,
+/// or if it tries to set a cookie for a domain that `url` does not belong to.
+///
+/// [RFC 6265 section 5.3]: https://tools.ietf.org/html/rfc6265#section-5.3
+pub(crate) fn parse_set_cookie(header: &str, url: &url::Url, now: u64) -> Option<Cookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+
+    let mut parts = header.split(';');
+    let pair = parts.next()?;
+    let eq = pair.find('=')?;
+    let name = pair[..eq].trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut c = Cookie::new(name, pair[eq + 1..].trim());
+
+    let mut max_age = None;
+    let mut expires = None;
+    for attr in parts {
+        let (key, value) = match attr.find('=') {
+            Some(eq) => (attr[..eq].trim(), attr[eq + 1..].trim()),
+            None => (attr.trim(), ""),
+        };
+
+        match &*key.to_ascii_lowercase() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_left_matches('.').to_ascii_lowercase();
+                if host != domain && !host.ends_with(&format!(".{}", domain)) {
+                    return None;
+                }
+                c.domain = Some(format!(".{}", domain));
+            }
+            "path" if value.starts_with('/') => c.path = Some(value.to_string()),
+            "secure" => c.secure = true,
+            "httponly" => c.http_only = true,
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "expires" => {
+                use std::time::UNIX_EPOCH;
+                expires = value
+                    .parse::<hyper::header::HttpDate>()
+                    .ok()
+                    .map(::std::time::SystemTime::from)
+                    .map(|t| {
+                        t.duration_since(UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0)
+                    });
+            }
+            "samesite" => c.same_site = SameSite::from_str(value),
+            _ => {}
+        }
+    }
+
+    // host-only cookies are reported without a leading .
+    if c.domain.is_none() {
+        c.domain = Some(host);
+    }
+
+    // https://tools.ietf.org/html/rfc6265#section-5.1.4
+    if c.path.is_none() {
+        let path = url.path();
+        let default = match path.rfind('/') {
+            Some(0) | None => "/",
+            Some(i) => &path[..i],
+        };
+        c.path = Some(default.to_string());
+    }
+
+    // Max-Age takes precedence over Expires
+    c.expiry = match max_age {
+        Some(age) if age <= 0 => Some(0),
+        Some(age) => Some(now + age as u64),
+        None => expires,
+    };
+
+    Some(c)
+}
+
+/// Add `cookie` to `jar`, replacing any existing cookie with the same name, domain, and path.
+///
+/// Expired cookies are removed from the jar rather than added.
+pub(crate) fn store(jar: &mut Vec<Cookie>, cookie: Cookie, now: u64) {
+    jar.retain(|c| c.name != cookie.name || c.domain != cookie.domain || c.path != cookie.path);
+    if cookie.expiry.map(|e| e > now).unwrap_or(true) {
+        jar.push(cookie);
+    }
+}
+
+impl Cookie {
+    /// Serialize this cookie as described in the [spec].
+    ///
+    /// [spec]: https://www.w3.org/TR/webdriver/#dfn-serialized-cookie
+    pub(crate) fn to_json(&self) -> Value {
+        let mut cookie = serde_json::Map::new();
+        cookie.insert("name".to_string(), Value::String(self.name.clone()));
+        cookie.insert("value".to_string(), Value::String(self.value.clone()));
+        if let Some(ref path) = self.path {
+            cookie.insert("path".to_string(), Value::String(path.clone()));
+        }
+        if let Some(ref domain) = self.domain {
+            cookie.insert("domain".to_string(), Value::String(domain.clone()));
+        }
+        cookie.insert("secure".to_string(), Value::Bool(self.secure));
+        cookie.insert("httpOnly".to_string(), Value::Bool(self.http_only));
+        if let Some(expiry) = self.expiry {
+            cookie.insert("expiry".to_string(), Value::from(expiry));
+        }
+        if let Some(same_site) = self.same_site {
+            cookie.insert(
+                "sameSite".to_string(),
+                Value::String(same_site.as_str().to_string()),
+            );
+        }
+        Value::Object(cookie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(domain: &str, path: &str) -> Cookie {
+        let mut c = Cookie::new("n", "v");
+        c.domain = Some(domain.to_string());
+        c.path = Some(path.to_string());
+        c
+    }
+
+    fn url(u: &str) -> url::Url {
+        u.parse().unwrap()
+    }
+
+    #[test]
+    fn domain_matching() {
+        let origin = url("https://www.example.com/");
+        let host_only = cookie("www.example.com", "/");
+        assert!(host_only.matches(&url("https://www.example.com/a"), &origin, 0));
+        assert!(!host_only.matches(&url("https://sub.www.example.com/a"), &origin, 0));
+        assert!(!host_only.matches(&url("https://example.com/a"), &origin, 0));
+
+        let domain = cookie(".example.com", "/");
+        assert!(domain.matches(&url("https://example.com/"), &origin, 0));
+        assert!(domain.matches(&url("https://cdn.example.com/"), &origin, 0));
+        assert!(!domain.matches(&url("https://badexample.com/"), &origin, 0));
+        assert!(!domain.matches(&url("https://other.org/"), &origin, 0));
+
+        let mut no_domain = Cookie::new("n", "v");
+        no_domain.path = Some("/".to_string());
+        assert!(no_domain.matches(&url("https://www.example.com/"), &origin, 0));
+        assert!(!no_domain.matches(&url("https://example.com/"), &origin, 0));
+    }
+
+    #[test]
+    fn path_matching() {
+        let origin = url("https://example.com/");
+        let c = cookie("example.com", "/download/some_identifier");
+        assert!(c.matches(
+            &url("https://example.com/download/some_identifier"),
+            &origin,
+            0
+        ));
+        assert!(c.matches(
+            &url("https://example.com/download/some_identifier/f.zip"),
+            &origin,
+            0
+        ));
+        assert!(!c.matches(
+            &url("https://example.com/download/some_identifier2"),
+            &origin,
+            0
+        ));
+        assert!(!c.matches(&url("https://example.com/download"), &origin, 0));
+
+        let c = cookie("example.com", "/download/");
+        assert!(c.matches(&url("https://example.com/download/x"), &origin, 0));
+    }
+
+    #[test]
+    fn devtools_cookies() {
+        let c = Cookie::from_cdp(&json!({
+            "name": "sid",
+            "value": "abc",
+            "domain": ".example.com",
+            "path": "/app/export",
+            "expires": 1700000000.5,
+            "size": 6,
+            "httpOnly": true,
+            "secure": true,
+            "session": false,
+            "sameSite": "Lax",
+        }))
+        .unwrap();
+        assert_eq!(c.name, "sid");
+        assert_eq!(c.domain, Some(".example.com".to_string()));
+        assert_eq!(c.path, Some("/app/export".to_string()));
+        assert!(c.http_only && c.secure);
+        assert_eq!(c.expiry, Some(1700000000));
+        assert_eq!(c.same_site, Some(SameSite::Lax));
+
+        let session = Cookie::from_cdp(&json!({
+            "name": "s",
+            "value": "",
+            "domain": "example.com",
+            "path": "/",
+            "expires": -1,
+            "httpOnly": false,
+            "secure": false,
+            "session": true,
+        }))
+        .unwrap();
+        assert_eq!(session.expiry, None);
+        assert_eq!(session.same_site, None);
+
+        assert!(Cookie::from_cdp(&json!({ "name": "s", "value": "" })).is_none());
+    }
+
+    #[test]
+    fn webdriver_cookies() {
+        let c = Cookie::from_json(&json!({
+            "name": "sid",
+            "value": "abc",
+            "path": "/",
+            "domain": ".example.com",
+            "secure": true,
+            "httpOnly": false,
+            "expiry": 1700000000,
+            "sameSite": "Strict",
+        }))
+        .unwrap();
+        assert_eq!(c.domain, Some(".example.com".to_string()));
+        assert_eq!(c.expiry, Some(1700000000));
+        assert_eq!(c.same_site, Some(SameSite::Strict));
+
+        let c = Cookie::from_json(&json!({
+            "name": "sid",
+            "value": "abc",
+            "sameSite": "no_restriction",
+        }))
+        .unwrap();
+        assert_eq!(c.same_site, None);
+    }
+
+    #[test]
+    fn secure_and_expiry() {
+        let origin = url("https://example.com/");
+        let mut c = cookie("example.com", "/");
+        c.secure = true;
+        assert!(c.matches(&url("https://example.com/"), &origin, 0));
+        assert!(!c.matches(&url("http://example.com/"), &origin, 0));
+
+        c.expiry = Some(100);
+        assert!(c.matches(&url("https://example.com/"), &origin, 99));
+        assert!(!c.matches(&url("https://example.com/"), &origin, 100));
+    }
+
+    #[test]
+    fn jar_orders_by_path_length() {
+        let origin = url("https://example.com/");
+        let mut short = cookie("example.com", "/");
+        short.name = "short".to_string();
+        let mut long = cookie("example.com", "/a/b");
+        long.name = "long".to_string();
+        let mut other = cookie("other.org", "/");
+        other.name = "other".to_string();
+
+        let jar = jar_for(
+            &[short, long, other],
+            &url("https://example.com/a/b/c"),
+            &origin,
+        );
+        assert_eq!(format!("{}", jar), "long=v; short=v");
+    }
+
+    #[test]
+    fn set_cookie() {
+        let u = url("https://www.example.com/download/file.zip");
+        let c = parse_set_cookie("sid=abc; Path=/; Secure; HttpOnly", &u, 0).unwrap();
+        assert_eq!(c.name, "sid");
+        assert_eq!(c.value, "abc");
+        assert_eq!(c.domain.as_ref().unwrap(), "www.example.com");
+        assert_eq!(c.path.as_ref().unwrap(), "/");
+        assert!(c.secure && c.http_only);
+        assert_eq!(c.expiry, None);
+
+        let c = parse_set_cookie("a=b; Domain=.Example.com; Max-Age=10", &u, 5).unwrap();
+        assert_eq!(c.domain.as_ref().unwrap(), ".example.com");
+        assert_eq!(c.path.as_ref().unwrap(), "/download");
+        assert_eq!(c.expiry, Some(15));
+
+        assert!(parse_set_cookie("a=b; Domain=other.org", &u, 0).is_none());
+        assert!(parse_set_cookie("garbage", &u, 0).is_none());
+    }
+
+    #[test]
+    fn store_replaces_and_expires() {
+        let u = url("https://example.com/");
+        let mut jar = Vec::new();
+        store(&mut jar, parse_set_cookie("a=1", &u, 0).unwrap(), 0);
+        store(&mut jar, parse_set_cookie("a=2", &u, 0).unwrap(), 0);
+        assert_eq!(jar.len(), 1);
+        assert_eq!(jar[0].value, "2");
+
+        store(
+            &mut jar,
+            parse_set_cookie("a=; Max-Age=0", &u, 0).unwrap(),
+            0,
+        );
+        assert!(jar.is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use cookies::{self, Cookie};
+use error;
+use hyper;
+use std::path::{Path, PathBuf};
+use url;
+
+/// The maximum number of redirects `Client::download` will follow.
+///
+/// This is the same limit that Firefox and Chrome use.
+pub(crate) const MAX_REDIRECTS: usize = 20;
+
+/// A summary of a file downloaded with `Client::download`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Download {
+    /// The path the response body was written to.
+    pub path: PathBuf,
+    /// The URL the file was eventually fetched from, after following any redirects.
+    pub url: url::Url,
+    /// The number of bytes written to `path`.
+    pub bytes: u64,
+    /// The `Content-Type` of the response, if the server gave one.
+    pub content_type: Option<String>,
+    /// The file name suggested by the server through `Content-Disposition`, if any.
+    pub suggested_filename: Option<String>,
+    /// The number of redirects that were followed.
+    pub redirects: usize,
+}
+
+/// Process the response `res` to a download request for `url`.
+///
+/// Any cookies the response sets are added to `jar`. If the response is a redirect, the URL to
+/// follow is returned. If it is a success, `None` is returned, and the body should be read.
+pub(crate) fn next_hop(
+    res: &hyper::Response,
+    url: &url::Url,
+    jar: &mut Vec<Cookie>,
+) -> Result<Option<url::Url>, error::CmdError> {
+    use hyper::StatusCode;
+
+    let now = cookies::now();
+    if let Some(set) = res.headers().get::<hyper::header::SetCookie>() {
+        for header in set.iter() {
+            if let Some(c) = cookies::parse_set_cookie(header, url, now) {
+                cookies::store(jar, c, now);
+            }
+        }
+    }
+
+    let status = res.status();
+    match status {
+        StatusCode::MovedPermanently
+        | StatusCode::Found
+        | StatusCode::SeeOther
+        | StatusCode::TemporaryRedirect
+        | StatusCode::PermanentRedirect => {
+            if let Some(next) = res.headers().get::<hyper::header::Location>() {
+                return Ok(Some(url.join(next)?));
+            }
+        }
+        _ if status.is_success() => return Ok(None),
+        _ => {}
+    }
+
+    Err(error::CmdError::Download(error::DownloadError::Status(
+        status,
+        url.clone(),
+    )))
+}
+
+/// Strip any directory components from a server-provided file name.
+///
+/// Servers control `Content-Disposition`, so we must not let them write outside of the directory
+/// the user asked for.
+fn sanitize(name: &str) -> Option<String> {
+    let name = name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or("");
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Extract the file name suggested by the `Content-Disposition` header of `res`, if any.
+pub(crate) fn suggested_filename(res: &hyper::Response) -> Option<String> {
+    use hyper::header::{ContentDisposition, DispositionParam};
+
+    // if both filename and filename* are given, the latter is generally given last, and is the
+    // one that should be preferred: https://tools.ietf.org/html/rfc6266#section-4.3
+    res.headers()
+        .get::<ContentDisposition>()?
+        .parameters
+        .iter()
+        .filter_map(|p| match *p {
+            DispositionParam::Filename(_, _, ref name) => sanitize(&String::from_utf8_lossy(name)),
+            _ => None,
+        })
+        .last()
+}
+
+/// Determine where to write a download of `url` given the user-provided `dest`.
+///
+/// If `dest` is an existing directory, the file is placed inside it, using the name suggested by
+/// the server or, failing that, the last segment of the URL's path.
+pub(crate) fn target_path(dest: PathBuf, suggested: Option<&str>, url: &url::Url) -> PathBuf {
+    if !dest.is_dir() {
+        return dest;
+    }
+
+    let name = suggested
+        .map(|s| s.to_string())
+        .or_else(|| {
+            url.path_segments()
+                .and_then(|s| s.last())
+                .and_then(sanitize)
+        })
+        .unwrap_or_else(|| "download".to_string());
+    dest.join(name)
+}
+
+/// The file a download to `path` is written to until it has completed.
+///
+/// It is a hidden sibling of `path`, so that it can be renamed into place, and so that an
+/// interrupted download never looks like a finished one.
+pub(crate) fn partial_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "download".to_string());
+    path.with_file_name(format!(".{}.part", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames_are_sanitized() {
+        assert_eq!(sanitize("report.pdf"), Some("report.pdf".to_string()));
+        assert_eq!(sanitize("../../etc/passwd"), Some("passwd".to_string()));
+        assert_eq!(sanitize("..\\evil.exe"), Some("evil.exe".to_string()));
+        assert_eq!(sanitize(".."), None);
+        assert_eq!(sanitize("dir/"), None);
+    }
+
+    #[test]
+    fn target_in_directory() {
+        use std::env;
+        let dir = env::temp_dir();
+        let url = "https://example.com/exports/data.csv".parse().unwrap();
+        assert_eq!(target_path(dir.clone(), None, &url), dir.join("data.csv"));
+        assert_eq!(
+            target_path(dir.clone(), Some("export.csv"), &url),
+            dir.join("export.csv")
+        );
+
+        let file = dir.join("definitely-not-a-directory.csv");
+        assert_eq!(target_path(file.clone(), Some("export.csv"), &url), file);
+    }
+
+    #[test]
+    fn partial_downloads_are_siblings() {
+        assert_eq!(
+            partial_path(Path::new("/tmp/exports/data.csv")),
+            Path::new("/tmp/exports/.data.csv.part")
+        );
+    }
+}
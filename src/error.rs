@@ -1,10 +1,15 @@
+use base64;
 use hyper::error as herror;
-use rustc_serialize::json;
+use hyper::StatusCode;
+use png;
+use serde_json;
 use std::error::Error;
 use std::fmt;
 use std::io::Error as IOError;
-use url::ParseError;
+use std::path::PathBuf;
+use url::{self, ParseError};
 use webdriver::error as wderror;
+use {InvalidControl, LoadState};
 
 /// An error occured while attempting to establish a session for a new `Client`.
 #[derive(Debug)]
@@ -16,7 +21,7 @@ pub enum NewSessionError {
     /// The connection to the WebDriver server was lost.
     Lost(IOError),
     /// The server did not give a WebDriver-conforming response.
-    NotW3C(json::Json),
+    NotW3C(serde_json::Value),
     /// The WebDriver server refused to create a new session.
     SessionNotCreated(wderror::WebDriverError),
 }
@@ -91,7 +96,7 @@ pub enum CmdError {
     NotJson(String),
 
     /// The WebDriver server responded to a command with an invalid JSON response.
-    Json(json::ParserError),
+    Json(serde_json::Error),
 
     /// The WebDriver server produced a response that does not conform to the [W3C WebDriver
     /// specification][spec].
@@ -102,10 +107,55 @@ pub enum CmdError {
     /// and does not correctly encode and decode `WebElement` references.
     ///
     /// [spec]: https://www.w3.org/TR/webdriver/
-    NotW3C(json::Json),
+    NotW3C(serde_json::Value),
 
     /// A function was invoked with an invalid argument.
     InvalidArgument(String, String),
+
+    /// A local file system operation, such as writing a downloaded file to disk, failed.
+    FileSystem(IOError),
+
+    /// A download issued through `Client::download` did not succeed.
+    Download(DownloadError),
+
+    /// The WebDriver server returned base64-encoded data (such as a screenshot) that could not be
+    /// decoded.
+    Base64(base64::DecodeError),
+
+    /// An image, such as a screenshot, could not be processed.
+    Image(ImageError),
+
+    /// The value returned by a script could not be deserialized into the requested type.
+    ///
+    /// The first field is the path to the part of the value that did not match, such as
+    /// `rows[2].title`.
+    TypeMismatch(String, serde_json::Error),
+
+    /// A form has no control with the given name.
+    NoSuchField(String),
+
+    /// Some of the fields passed to `Form::fill` could not be set.
+    ///
+    /// Holds the name of each such field along with what went wrong.
+    FormFill(Vec<(String, CmdError)>),
+
+    /// A form was not submitted because some of its controls failed HTML5 validation.
+    ///
+    /// This is only returned for forms that have opted in with `Form::fail_on_invalid`.
+    InvalidForm(Vec<InvalidControl>),
+
+    /// No new page loaded in time after an action passed to `Client::expect_navigation`.
+    ///
+    /// Holds the URL of the page that was current before the action.
+    NavigationTimeout(url::Url),
+
+    /// The page did not reach the given state in time in `Client::wait_for_load_state`.
+    LoadTimeout(LoadState),
+
+    /// The page did not get the expected title in time in `Client::wait_for_title`.
+    ///
+    /// The title the page had last is included.
+    TitleTimeout(String),
 }
 
 impl CmdError {
@@ -142,6 +192,17 @@ impl Error for CmdError {
             CmdError::Json(..) => "webdriver returned incoherent response",
             CmdError::NotW3C(..) => "webdriver returned non-conforming response",
             CmdError::InvalidArgument(..) => "invalid argument provided",
+            CmdError::FileSystem(..) => "local file system operation failed",
+            CmdError::Download(..) => "download failed",
+            CmdError::Base64(..) => "webdriver returned undecodable data",
+            CmdError::Image(..) => "image could not be processed",
+            CmdError::TypeMismatch(..) => "script returned value of unexpected type",
+            CmdError::NoSuchField(..) => "no form control with the given name",
+            CmdError::FormFill(..) => "some form fields could not be set",
+            CmdError::InvalidForm(..) => "form failed validation",
+            CmdError::NavigationTimeout(..) => "no new page loaded in time",
+            CmdError::LoadTimeout(..) => "page did not load in time",
+            CmdError::TitleTimeout(..) => "page did not get the expected title in time",
         }
     }
 
@@ -152,7 +213,20 @@ impl Error for CmdError {
             CmdError::Failed(ref e) => Some(e),
             CmdError::Lost(ref e) => Some(e),
             CmdError::Json(ref e) => Some(e),
-            CmdError::NotJson(_) | CmdError::NotW3C(_) | CmdError::InvalidArgument(..) => None,
+            CmdError::FileSystem(ref e) => Some(e),
+            CmdError::Download(ref e) => Some(e),
+            CmdError::Base64(ref e) => Some(e),
+            CmdError::Image(ref e) => Some(e),
+            CmdError::TypeMismatch(_, ref e) => Some(e),
+            CmdError::NotJson(_)
+            | CmdError::NotW3C(_)
+            | CmdError::InvalidArgument(..)
+            | CmdError::NoSuchField(_)
+            | CmdError::FormFill(_)
+            | CmdError::InvalidForm(_)
+            | CmdError::NavigationTimeout(_)
+            | CmdError::LoadTimeout(_)
+            | CmdError::TitleTimeout(_) => None,
         }
     }
 }
@@ -171,10 +245,158 @@ impl fmt::Display for CmdError {
             CmdError::InvalidArgument(ref arg, ref msg) => {
                 write!(f, "Invalid argument `{}`: {}", arg, msg)
             }
+            CmdError::FileSystem(ref e) => write!(f, "{}", e),
+            CmdError::Download(ref e) => write!(f, "{}", e),
+            CmdError::Base64(ref e) => write!(f, "{}", e),
+            CmdError::Image(ref e) => write!(f, "{}", e),
+            CmdError::TypeMismatch(ref path, ref e) => write!(f, "at `{}`: {}", path, e),
+            CmdError::NoSuchField(ref name) => write!(f, "`{}`", name),
+            CmdError::FormFill(ref errors) => {
+                for (i, &(ref name, ref e)) in errors.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "`{}`: {}", name, e)?;
+                }
+                Ok(())
+            }
+            CmdError::InvalidForm(ref invalid) => {
+                for (i, c) in invalid.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "`{}`: {}", c.name, c.message)?;
+                }
+                Ok(())
+            }
+            CmdError::NavigationTimeout(ref url) => write!(f, "still at {}", url),
+            CmdError::LoadTimeout(ref state) => write!(f, "waiting for {:?}", state),
+            CmdError::TitleTimeout(ref title) => write!(f, "title is still {:?}", title),
         }
     }
 }
 
+/// An error occurred while downloading a file through `Client::download`.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The server responded to the request for the given URL with a non-successful status.
+    Status(StatusCode, url::Url),
+
+    /// The server kept redirecting; the given URL is the last redirect target.
+    TooManyRedirects(url::Url),
+}
+
+impl Error for DownloadError {
+    fn description(&self) -> &str {
+        match *self {
+            DownloadError::Status(..) => "server returned unsuccessful status",
+            DownloadError::TooManyRedirects(..) => "too many redirects",
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.description())?;
+        match *self {
+            DownloadError::Status(ref status, ref url) => write!(f, "{} for {}", status, url),
+            DownloadError::TooManyRedirects(ref url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// An error occurred while decoding or encoding an image.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The image is not a valid PNG.
+    Decode(png::DecodingError),
+
+    /// The image could not be encoded as a PNG.
+    Encode(png::EncodingError),
+
+    /// The image uses a pixel format that is not supported.
+    Unsupported(String),
+}
+
+impl Error for ImageError {
+    fn description(&self) -> &str {
+        match *self {
+            ImageError::Decode(..) => "invalid png image",
+            ImageError::Encode(..) => "png encoding failed",
+            ImageError::Unsupported(..) => "unsupported pixel format",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            ImageError::Decode(ref e) => Some(e),
+            ImageError::Encode(ref e) => Some(e),
+            ImageError::Unsupported(..) => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.description())?;
+        match *self {
+            ImageError::Decode(ref e) => write!(f, "{}", e),
+            ImageError::Encode(ref e) => write!(f, "{}", e),
+            ImageError::Unsupported(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// An error occurred while comparing an image against a baseline with `visual::compare`.
+#[derive(Debug)]
+pub enum VisualError {
+    /// The baseline image does not exist.
+    ///
+    /// Set `visual::UPDATE_BASELINES_VAR` to create it.
+    MissingBaseline(PathBuf),
+
+    /// The baseline or diff image could not be read or written.
+    Io(IOError),
+
+    /// One of the images could not be decoded or encoded.
+    Image(ImageError),
+}
+
+impl Error for VisualError {
+    fn description(&self) -> &str {
+        match *self {
+            VisualError::MissingBaseline(..) => "baseline image does not exist",
+            VisualError::Io(..) => "could not access image file",
+            VisualError::Image(..) => "image could not be processed",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            VisualError::MissingBaseline(..) => None,
+            VisualError::Io(ref e) => Some(e),
+            VisualError::Image(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for VisualError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.description())?;
+        match *self {
+            VisualError::MissingBaseline(ref p) => write!(f, "{}", p.display()),
+            VisualError::Io(ref e) => write!(f, "{}", e),
+            VisualError::Image(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ImageError> for CmdError {
+    fn from(e: ImageError) -> Self {
+        CmdError::Image(e)
+    }
+}
+
 impl From<IOError> for CmdError {
     fn from(e: IOError) -> Self {
         CmdError::Lost(e)
@@ -207,8 +429,8 @@ impl From<wderror::WebDriverError> for CmdError {
     }
 }
 
-impl From<json::ParserError> for CmdError {
-    fn from(e: json::ParserError) -> Self {
+impl From<serde_json::Error> for CmdError {
+    fn from(e: serde_json::Error) -> Self {
         CmdError::Json(e)
     }
 }
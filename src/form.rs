@@ -0,0 +1,841 @@
+use error;
+use futures::{future, stream, Future, Stream};
+use serde;
+use serde_json::{self, Value};
+use webdriver::command::{SendKeysParameters, WebDriverCommand};
+use webdriver::common::WebElement;
+use {element_ref, Client, Form, ScriptValue};
+
+/// Helpers shared by the scripts that change form controls.
+///
+/// `findControls(form, name)` looks up controls through `form.elements`, so controls associated
+/// with the form through their `form` attribute are found too, and then among the form's
+/// `contenteditable` descendants.
+///
+/// `setControl(form, name, value)` sets the named control. `value` is a string, a boolean for
+/// checkboxes, or an array of strings for checkbox groups and `<select multiple>`s. It returns
+/// `null` on success, or an object describing what went wrong. `input` and `change` events are
+/// fired for every control that changed.
+const CONTROL_LIB: &str = "\
+    var kind = function(el) {\
+        if (el.tagName === 'INPUT') { return el.type; }\
+        if (el.tagName === 'SELECT' || el.tagName === 'TEXTAREA') {\
+            return el.tagName.toLowerCase();\
+        }\
+        return el.isContentEditable ? 'contenteditable' : el.tagName.toLowerCase();\
+    };\
+    var fire = function(el) {\
+        el.dispatchEvent(new Event('input', { bubbles: true }));\
+        if (kind(el) !== 'contenteditable') {\
+            el.dispatchEvent(new Event('change', { bubbles: true }));\
+        }\
+    };\
+    var findControls = function(form, name) {\
+        var controls = [], i;\
+        for (i = 0; i < form.elements.length; i++) {\
+            if (form.elements[i].name === name) { controls.push(form.elements[i]); }\
+        }\
+        if (!controls.length) {\
+            var named = form.querySelectorAll('[name]');\
+            for (i = 0; i < named.length; i++) {\
+                if (named[i].getAttribute('name') === name && named[i].isContentEditable) {\
+                    controls.push(named[i]);\
+                }\
+            }\
+        }\
+        return controls;\
+    };\
+    var setControl = function(form, name, value) {\
+        var controls = findControls(form, name), i;\
+        if (!controls.length) { return { error: 'missing' }; }\
+        var first = controls[0], type = kind(first);\
+        var typeError = function(t, expected) {\
+            return { error: 'type', type: t, expected: expected };\
+        };\
+        if (typeof value === 'boolean') {\
+            for (i = 0; i < controls.length; i++) {\
+                if (kind(controls[i]) !== 'checkbox') {\
+                    return typeError(kind(controls[i]), 'a checkbox');\
+                }\
+            }\
+            for (i = 0; i < controls.length; i++) {\
+                if (controls[i].checked !== value) {\
+                    controls[i].checked = value;\
+                    fire(controls[i]);\
+                }\
+            }\
+            return null;\
+        }\
+        var many = Array.isArray(value), values = many ? value : [value];\
+        if (many && type !== 'checkbox' && !(type === 'select' && first.multiple)) {\
+            return typeError(first.type || type, 'a checkbox or a <select multiple>');\
+        }\
+        var pick = function(choices) {\
+            for (var j = 0; j < values.length; j++) {\
+                if (choices.indexOf(values[j]) === -1) {\
+                    return { error: 'value', choices: choices, value: values[j] };\
+                }\
+            }\
+            return null;\
+        };\
+        var choices = [], missing, on;\
+        if (type === 'radio' || type === 'checkbox') {\
+            var group = [];\
+            for (i = 0; i < controls.length; i++) {\
+                if (kind(controls[i]) === type) {\
+                    group.push(controls[i]);\
+                    choices.push(controls[i].value);\
+                }\
+            }\
+            missing = pick(choices);\
+            if (missing) { return missing; }\
+            var done = false;\
+            for (i = 0; i < group.length; i++) {\
+                on = values.indexOf(group[i].value) !== -1 && (many || !done);\
+                done = done || on;\
+                if (on && !group[i].checked) { group[i].checked = true; fire(group[i]); }\
+                if (!on && many && group[i].checked) { group[i].checked = false; fire(group[i]); }\
+            }\
+            return null;\
+        }\
+        if (type === 'select') {\
+            for (i = 0; i < first.options.length; i++) { choices.push(first.options[i].value); }\
+            missing = pick(choices);\
+            if (missing) { return missing; }\
+            var found = false, changed = false;\
+            for (i = 0; i < first.options.length; i++) {\
+                var o = first.options[i];\
+                on = values.indexOf(o.value) !== -1 && (first.multiple || !found);\
+                found = found || on;\
+                if (o.selected !== on) { o.selected = on; changed = true; }\
+            }\
+            if (changed) { fire(first); }\
+            return null;\
+        }\
+        if (type === 'file') { return typeError(type, 'a control that takes a value'); }\
+        if (type === 'contenteditable') {\
+            if (first.textContent !== value) { first.textContent = value; fire(first); }\
+            return null;\
+        }\
+        if (first.value !== value) { first.value = value; fire(first); }\
+        return null;\
+    };";
+
+/// The script behind `Form::set` and `Form::set_checked`.
+///
+/// It is called with the form, the name of the control, and the value to set.
+const SET_CONTROL: &str = "return setControl(arguments[0], arguments[1], arguments[2]);";
+
+/// The script behind `Form::fill` and `Form::fill_from`.
+///
+/// It is called with the form and a list of `[name, value]` pairs, and returns a list of
+/// `[name, error]` pairs for the fields that could not be set.
+const FILL: &str = "\
+    var form = arguments[0], fields = arguments[1], errors = [];\
+    for (var f = 0; f < fields.length; f++) {\
+        var err = setControl(form, fields[f][0], fields[f][1]);\
+        if (err) { errors.push([fields[f][0], err]); }\
+    }\
+    return errors;";
+
+/// The script that prepares a field to be typed into by `Form::fill_typing`.
+///
+/// It is called with the form and the name of the control. If the control is a single control
+/// that takes text, it is cleared and returned. Otherwise, `null` is returned, and the field is
+/// set with `setControl` instead.
+const TYPING_TARGET: &str = "\
+    var controls = findControls(arguments[0], arguments[1]);\
+    if (controls.length !== 1) { return null; }\
+    var el = controls[0], type = kind(el);\
+    var text = ['text', 'search', 'email', 'password', 'tel', 'url', 'number', 'textarea'];\
+    if (type === 'contenteditable') {\
+        el.textContent = '';\
+    } else if (text.indexOf(type) !== -1) {\
+        el.value = '';\
+    } else {\
+        return null;\
+    }\
+    return el;";
+
+/// The script behind `Form::data`.
+///
+/// This follows the HTML algorithm for [constructing the entry list], as if the form was
+/// submitted without a submit button. It returns a list of `[name, value]` pairs.
+///
+/// [constructing the entry list]: https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constructing-the-form-data-set
+const DATA: &str = "\
+    var form = arguments[0], data = [], i, j;\
+    for (i = 0; i < form.elements.length; i++) {\
+        var el = form.elements[i], tag = el.tagName, type = (el.type || '').toLowerCase();\
+        if (['BUTTON', 'INPUT', 'SELECT', 'TEXTAREA'].indexOf(tag) === -1) { continue; }\
+        var p = el.parentNode, in_datalist = false;\
+        for (; p; p = p.parentNode) { if (p.tagName === 'DATALIST') { in_datalist = true; } }\
+        if (in_datalist || el.disabled || (el.matches && el.matches(':disabled'))) { continue; }\
+        if (tag === 'BUTTON' || ['button', 'submit', 'reset', 'image'].indexOf(type) !== -1) {\
+            continue;\
+        }\
+        if ((type === 'checkbox' || type === 'radio') && !el.checked) { continue; }\
+        if (!el.name) { continue; }\
+        if (tag === 'SELECT') {\
+            for (j = 0; j < el.options.length; j++) {\
+                var o = el.options[j];\
+                if (o.selected && !o.disabled) { data.push([el.name, o.value]); }\
+            }\
+        } else if (type === 'file') {\
+            if (!el.files || !el.files.length) { data.push([el.name, '']); }\
+            for (j = 0; el.files && j < el.files.length; j++) {\
+                data.push([el.name, el.files[j].name]);\
+            }\
+        } else if (type === 'hidden' && el.name === '_charset_') {\
+            data.push([el.name, document.characterSet || document.charset || 'UTF-8']);\
+        } else {\
+            data.push([el.name, el.value]);\
+        }\
+        if (el.dirName && (tag === 'TEXTAREA' || ['text', 'search'].indexOf(type) !== -1)) {\
+            data.push([el.dirName, window.getComputedStyle(el).direction === 'rtl' ? 'rtl' : 'ltr']);\
+        }\
+    }\
+    return data;";
+
+/// The script behind `Form::controls`.
+const CONTROLS: &str = "\
+    var form = arguments[0], controls = [];\
+    for (var i = 0; i < form.elements.length; i++) {\
+        var el = form.elements[i];\
+        controls.push({\
+            name: el.name || el.getAttribute('name') || '',\
+            type: (el.type || el.tagName).toLowerCase(),\
+            disabled: !!(el.disabled || (el.matches && el.matches(':disabled')))\
+        });\
+    }\
+    return controls;";
+
+/// The script behind `Form::validate`, and `fail_on_invalid`.
+///
+/// It is called with the form and the button that will submit it, if any. Each control is
+/// checked with `checkValidity`, which also fires `invalid` events like the browser does. When a
+/// button is given, the form's `novalidate` and the button's `formnovalidate` are respected.
+const VALIDATE: &str = "\
+    var form = arguments[0], submitter = arguments[1], invalid = [];\
+    if (submitter && (form.noValidate || submitter.formNoValidate)) { return invalid; }\
+    for (var i = 0; i < form.elements.length; i++) {\
+        var el = form.elements[i];\
+        if (el.willValidate && !el.checkValidity()) {\
+            invalid.push({ name: el.name || '', message: el.validationMessage });\
+        }\
+    }\
+    return invalid;";
+
+/// A control that belongs to a form, as listed by `Form::controls`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FormControl {
+    /// The name the control submits its value under, or the empty string if it has none.
+    pub name: String,
+    /// The [type] of the control, such as `text`, `checkbox`, `select-one`, or `textarea`.
+    ///
+    /// [type]: https://html.spec.whatwg.org/multipage/forms.html#dom-input-type
+    pub kind: String,
+    /// Whether the control is disabled, either by itself or by a `<fieldset>` it is in.
+    pub disabled: bool,
+}
+
+impl FormControl {
+    /// Parse a control as returned by `CONTROLS`.
+    fn from_json(c: &Value) -> Option<Self> {
+        Some(FormControl {
+            name: c.get("name")?.as_str()?.to_string(),
+            kind: c.get("type")?.as_str()?.to_string(),
+            disabled: c.get("disabled")?.as_bool()?,
+        })
+    }
+}
+
+/// A control that failed [constraint validation], as reported by `Form::validate`.
+///
+/// [constraint validation]: https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constraints
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InvalidControl {
+    /// The name of the control, or the empty string if it has none.
+    pub name: String,
+    /// The message the browser would show the user, such as "Please fill out this field."
+    ///
+    /// The wording depends on the browser and its locale.
+    pub message: String,
+}
+
+impl InvalidControl {
+    /// Parse a control as returned by `VALIDATE`.
+    fn from_json(c: &Value) -> Option<Self> {
+        Some(InvalidControl {
+            name: c.get("name")?.as_str()?.to_string(),
+            message: c.get("message")?.as_str()?.to_string(),
+        })
+    }
+}
+
+impl Form {
+    /// Set the control with the given `name` in this form to `value`.
+    ///
+    /// Unlike `set_by_name`, this finds any kind of named control, and sets it the way that suits
+    /// it:
+    ///
+    ///  - text-like inputs and `<textarea>`s have their value replaced;
+    ///  - `<select>`s have the option with the given value selected, and all others deselected;
+    ///  - in a group of radio buttons or checkboxes, the one with the given value is checked;
+    ///  - elements with `contenteditable` have their text replaced.
+    ///
+    /// `input` and `change` events are fired for any control that changed. If the form has no
+    /// control with the given name, `CmdError::NoSuchField` is returned. If the control does not
+    /// offer the given value, as for a `<select>` without a matching option, a
+    /// `CmdError::InvalidArgument` listing the available values is returned.
+    pub fn set(
+        &self,
+        name: &str,
+        value: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set_control(name, Value::from(value))
+    }
+
+    /// Check or uncheck the checkbox with the given `name` in this form.
+    ///
+    /// If several checkboxes share the name, they are all changed. If the named control is not a
+    /// checkbox, a `CmdError::InvalidArgument` is returned.
+    pub fn set_checked(
+        &self,
+        name: &str,
+        checked: bool,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set_control(name, Value::Bool(checked))
+    }
+
+    /// Set many controls of this form at once.
+    ///
+    /// Each field is set like `set` would, all in a single script. Values can be strings, numbers,
+    /// booleans (to check or uncheck a checkbox), or arrays of strings (to choose exactly those
+    /// values in a group of checkboxes or a `<select multiple>`). `null` values are skipped.
+    ///
+    /// Every field is attempted even if some fail. If any do, `CmdError::FormFill` is returned
+    /// with the error for each field that could not be set.
+    ///
+    /// ```no_run
+    /// # extern crate tokio_core;
+    /// # extern crate futures;
+    /// # extern crate fantoccini;
+    /// # extern crate serde_json;
+    /// # fn main() {
+    /// use fantoccini::{Client, Locator};
+    /// use futures::future::Future;
+    /// use serde_json::Value;
+    /// let mut core = tokio_core::reactor::Core::new().unwrap();
+    /// let c = core.run(Client::new("http://localhost:4444", &core.handle())).unwrap();
+    /// let f = c.form(Locator::Css("#signup")).and_then(|f| {
+    ///     f.fill(vec![
+    ///         ("name", Value::from("Jane Doe")),
+    ///         ("age", Value::from(42)),
+    ///         ("newsletter", Value::from(true)),
+    ///         ("interests", Value::from(vec!["rust", "webdriver"])),
+    ///     ])
+    /// });
+    /// core.run(f).unwrap();
+    /// # }
+    /// ```
+    pub fn fill<I, K, V>(
+        &self,
+        fields: I,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        let fields = fields
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        self.fill_fields(fields)
+    }
+
+    /// Set many controls of this form at once, from the fields of `data`.
+    ///
+    /// `data` must serialize to a map, such as a struct or a `HashMap`, from control names to
+    /// values. Values are treated as by `fill`, so `Option` fields that are `None` are skipped.
+    /// Note that `serde_json` sorts the fields by name unless its `preserve_order` feature is
+    /// enabled.
+    pub fn fill_from<T: serde::Serialize>(
+        &self,
+        data: &T,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let invalid = |msg: String| error::CmdError::InvalidArgument("data".to_string(), msg);
+        match serde_json::to_value(data) {
+            Ok(Value::Object(fields)) => {
+                future::Either::A(self.fill_fields(fields.into_iter().collect()))
+            }
+            Ok(v) => future::Either::B(future::err(invalid(format!(
+                "Expected to serialize to a map but was `{}`",
+                v
+            )))),
+            Err(e) => future::Either::B(future::err(invalid(e.to_string()))),
+        }
+    }
+
+    /// Set many controls of this form, one at a time, by typing into them.
+    ///
+    /// This is slower than `fill`, but fields that take text are cleared and then typed into
+    /// with real key events, for pages that react to `keydown` or `keyup`. Other fields are set
+    /// like `fill` would. Fields are filled in order, and errors are reported as for `fill`.
+    pub fn fill_typing<I, K, V>(
+        &self,
+        fields: I,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        let fields = fields
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        let order = field_names(&fields);
+        let (fields, errors) = prepare_fields(fields);
+        let f = self.dup();
+
+        stream::iter_ok::<_, error::CmdError>(fields)
+            .fold((f, errors), |(f, mut errors), (name, value)| {
+                f.type_field(&name, value).then(move |r| {
+                    if let Err(e) = r {
+                        errors.push((name, e));
+                    }
+                    Ok::<_, error::CmdError>((f, errors))
+                })
+            })
+            .and_then(move |(f, mut errors)| {
+                in_field_order(&order, &mut errors);
+                if errors.is_empty() {
+                    Ok(f)
+                } else {
+                    Err(error::CmdError::FormFill(errors))
+                }
+            })
+    }
+
+    /// Get the data this form would currently submit, as a list of name/value pairs.
+    ///
+    /// This follows the HTML form submission algorithm, so disabled controls, unchecked
+    /// checkboxes and radio buttons, and buttons are left out, and a `<select multiple>` gives one
+    /// pair for each selected option. Pairs are in the order the controls appear in the form.
+    /// For file inputs, the value is the name of each selected file.
+    pub fn data(
+        &self,
+    ) -> impl Future<Item = Vec<(String, String)>, Error = error::CmdError> + 'static {
+        self.c
+            .execute(DATA, vec![element_ref(&self.f)])
+            .and_then(|data| parse_pairs(&data).ok_or_else(|| error::CmdError::NotW3C(data)))
+    }
+
+    /// List the controls of this form, in order.
+    ///
+    /// This includes controls that are associated with the form through their `form` attribute.
+    pub fn controls(
+        &self,
+    ) -> impl Future<Item = Vec<FormControl>, Error = error::CmdError> + 'static {
+        self.c
+            .execute(CONTROLS, vec![element_ref(&self.f)])
+            .and_then(|controls| {
+                let parsed = match controls.as_array() {
+                    Some(cs) => cs
+                        .iter()
+                        .map(FormControl::from_json)
+                        .collect::<Option<Vec<_>>>(),
+                    None => None,
+                };
+
+                parsed.ok_or_else(|| error::CmdError::NotW3C(controls))
+            })
+    }
+
+    /// Check the current values of this form against its HTML5 constraints, such as `required`
+    /// or `pattern`.
+    ///
+    /// Returns the controls that are invalid, in order, which is empty if the form is valid. The
+    /// browser fires `invalid` events for those controls, just like when the user tries to submit
+    /// the form.
+    pub fn validate(
+        &self,
+    ) -> impl Future<Item = Vec<InvalidControl>, Error = error::CmdError> + 'static {
+        let args = vec![element_ref(&self.f), Value::Null];
+        self.c.execute(VALIDATE, args).and_then(parse_invalid)
+    }
+
+    fn set_control(
+        &self,
+        name: &str,
+        value: Value,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let f = self.dup();
+        let name = name.to_string();
+        let args = vec![element_ref(&self.f), Value::from(&*name), value];
+
+        self.c
+            .execute(&with_lib(SET_CONTROL), args)
+            .and_then(move |res| control_result(&name, res))
+            .map(move |_| f)
+    }
+
+    fn fill_fields(
+        &self,
+        fields: Vec<(String, Value)>,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let f = self.dup();
+        let order = field_names(&fields);
+        let (fields, mut errors) = prepare_fields(fields);
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| json!([name, value]))
+            .collect();
+        let args = vec![element_ref(&self.f), Value::Array(fields)];
+
+        self.c.execute(&with_lib(FILL), args).and_then(move |res| {
+            errors.extend(fill_errors(res)?);
+            in_field_order(&order, &mut errors);
+            if errors.is_empty() {
+                Ok(f)
+            } else {
+                Err(error::CmdError::FormFill(errors))
+            }
+        })
+    }
+
+    /// Set a single field for `fill_typing`.
+    fn type_field(
+        &self,
+        name: &str,
+        value: Value,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'static {
+        let text = match value {
+            Value::String(ref text) => text.clone(),
+            _ => return future::Either::B(self.set_control(name, value).map(|_| ())),
+        };
+
+        let f = self.dup();
+        let name = name.to_string();
+        let args = vec![element_ref(&self.f), Value::from(&*name)];
+        future::Either::A(
+            self.c
+                .execute_with_elements(&with_lib(TYPING_TARGET), args)
+                .and_then(move |target| match target {
+                    ScriptValue::Element(e) => {
+                        let cmd =
+                            WebDriverCommand::ElementSendKeys(e.e, SendKeysParameters { text });
+                        future::Either::A(f.c.issue_wd_cmd(cmd).map(|_| ()))
+                    }
+                    _ => future::Either::B(f.set_control(&name, value).map(|_| ())),
+                }),
+        )
+    }
+}
+
+/// Prefix `script` with the definitions from `CONTROL_LIB`.
+fn with_lib(script: &str) -> String {
+    format!("{}\n{}", CONTROL_LIB, script)
+}
+
+/// Normalize the values of fields to be filled in.
+///
+/// Fields with `null` values are dropped, and numbers are turned into strings. Fields with values
+/// that no control can take are split off with an error.
+fn prepare_fields(
+    fields: Vec<(String, Value)>,
+) -> (Vec<(String, Value)>, Vec<(String, error::CmdError)>) {
+    let mut ok = Vec::new();
+    let mut errors = Vec::new();
+    for (name, value) in fields {
+        let value = match value {
+            Value::Null => continue,
+            Value::Number(n) => Value::String(n.to_string()),
+            Value::Array(vs) => {
+                if vs.iter().all(Value::is_string) {
+                    Value::Array(vs)
+                } else {
+                    let msg = format!(
+                        "Expected to be an array of strings but was `{}`",
+                        Value::Array(vs)
+                    );
+                    errors.push((name.clone(), error::CmdError::InvalidArgument(name, msg)));
+                    continue;
+                }
+            }
+            Value::Object(o) => {
+                let msg = format!(
+                    "Expected to be a string, number, boolean, or array but was `{}`",
+                    Value::Object(o)
+                );
+                errors.push((name.clone(), error::CmdError::InvalidArgument(name, msg)));
+                continue;
+            }
+            v => v,
+        };
+        ok.push((name, value));
+    }
+    (ok, errors)
+}
+
+/// The names of `fields`, in order.
+fn field_names(fields: &[(String, Value)]) -> Vec<String> {
+    fields.iter().map(|&(ref name, _)| name.clone()).collect()
+}
+
+/// Sort `errors` by where their field appears in `order`.
+///
+/// Fields with invalid values are rejected before the others are set, so their errors are
+/// collected first. The sort is stable, so repeated fields keep their relative order.
+fn in_field_order(order: &[String], errors: &mut Vec<(String, error::CmdError)>) {
+    errors.sort_by_key(|&(ref name, _)| order.iter().position(|n| n == name));
+}
+
+/// Check whether the browser would block submitting `form` with the button `submitter`.
+///
+/// If it would, `CmdError::InvalidForm` is returned. Otherwise, the arguments are passed on.
+pub(crate) fn check_submission(
+    c: Client,
+    form: &WebElement,
+    submitter: WebElement,
+) -> impl Future<Item = (Client, WebElement), Error = error::CmdError> + 'static {
+    let args = vec![element_ref(form), element_ref(&submitter)];
+    c.execute(VALIDATE, args)
+        .and_then(parse_invalid)
+        .and_then(move |invalid| {
+            if invalid.is_empty() {
+                Ok((c, submitter))
+            } else {
+                Err(error::CmdError::InvalidForm(invalid))
+            }
+        })
+}
+
+/// Parse the list of invalid controls returned by `VALIDATE`.
+fn parse_invalid(invalid: Value) -> Result<Vec<InvalidControl>, error::CmdError> {
+    let parsed = match invalid.as_array() {
+        Some(cs) => cs
+            .iter()
+            .map(InvalidControl::from_json)
+            .collect::<Option<Vec<_>>>(),
+        None => None,
+    };
+
+    parsed.ok_or_else(|| error::CmdError::NotW3C(invalid))
+}
+
+/// Parse the list of `[name, value]` pairs returned by `DATA`.
+fn parse_pairs(data: &Value) -> Option<Vec<(String, String)>> {
+    data.as_array()?
+        .iter()
+        .map(|pair| match pair.as_array().map(|p| &p[..]) {
+            Some(&[Value::String(ref name), Value::String(ref value)]) => {
+                Some((name.clone(), value.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Interpret the list of `[name, error]` pairs returned by `FILL`.
+fn fill_errors(res: Value) -> Result<Vec<(String, error::CmdError)>, error::CmdError> {
+    let parsed = res.as_array().and_then(|pairs| {
+        pairs
+            .iter()
+            .map(|pair| {
+                let name = pair.get(0)?.as_str()?;
+                Some((name.to_string(), control_error(name, pair.get(1)?)?))
+            })
+            .collect::<Option<Vec<_>>>()
+    });
+
+    parsed.ok_or_else(|| error::CmdError::NotW3C(res))
+}
+
+/// Interpret the result of `setControl`.
+fn control_result(name: &str, res: Value) -> Result<(), error::CmdError> {
+    if res.is_null() {
+        return Ok(());
+    }
+
+    Err(control_error(name, &res).unwrap_or_else(|| error::CmdError::NotW3C(res)))
+}
+
+/// Turn an error object returned by `setControl` into the matching `CmdError`.
+fn control_error(name: &str, res: &Value) -> Option<error::CmdError> {
+    let field = |key: &str| res.get(key).and_then(Value::as_str);
+    let msg = match field("error")? {
+        "missing" => return Some(error::CmdError::NoSuchField(name.to_string())),
+        "type" => format!(
+            "Expected to be {} but was `{}`",
+            field("expected")?,
+            field("type")?
+        ),
+        "value" => {
+            let choices = res
+                .get("choices")?
+                .as_array()?
+                .iter()
+                .map(|c| c.as_str().map(|c| format!("`{}`", c)))
+                .collect::<Option<Vec<_>>>()?;
+            format!(
+                "Expected to be one of {} but was `{}`",
+                choices.join(", "),
+                field("value")?
+            )
+        }
+        _ => return None,
+    };
+
+    Some(error::CmdError::InvalidArgument(name.to_string(), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_pairs() {
+        let data = json!([["q", "rust"], ["tag", "a"], ["tag", "b"], ["empty", ""]]);
+        assert_eq!(
+            parse_pairs(&data).unwrap(),
+            vec![
+                ("q".to_string(), "rust".to_string()),
+                ("tag".to_string(), "a".to_string()),
+                ("tag".to_string(), "b".to_string()),
+                ("empty".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(parse_pairs(&json!([])), Some(vec![]));
+        assert_eq!(parse_pairs(&json!([["q"]])), None);
+        assert_eq!(parse_pairs(&json!([["q", 1]])), None);
+        assert_eq!(parse_pairs(&json!({})), None);
+    }
+
+    #[test]
+    fn controls() {
+        let c = FormControl::from_json(&json!({
+            "name": "language",
+            "type": "select-one",
+            "disabled": false,
+        }))
+        .unwrap();
+        assert_eq!(c.name, "language");
+        assert_eq!(c.kind, "select-one");
+        assert!(!c.disabled);
+        assert!(FormControl::from_json(&json!({ "name": "x" })).is_none());
+    }
+
+    #[test]
+    fn invalid_controls() {
+        let invalid = parse_invalid(json!([
+            { "name": "email", "message": "Please enter an email address." },
+            { "name": "", "message": "Please fill out this field." },
+        ]))
+        .unwrap();
+        assert_eq!(invalid.len(), 2);
+        assert_eq!(invalid[0].name, "email");
+        assert_eq!(invalid[0].message, "Please enter an email address.");
+        assert_eq!(invalid[1].name, "");
+
+        assert!(parse_invalid(json!([])).unwrap().is_empty());
+        assert!(parse_invalid(json!([{ "name": "email" }])).is_err());
+        assert!(parse_invalid(Value::Null).is_err());
+    }
+
+    #[test]
+    fn control_results() {
+        assert!(control_result("a", Value::Null).is_ok());
+
+        match control_result("a", json!({ "error": "missing" })) {
+            Err(error::CmdError::NoSuchField(ref name)) => assert_eq!(name, "a"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        let res = json!({ "error": "value", "choices": ["en", "de"], "value": "fr" });
+        match control_result("lang", res) {
+            Err(error::CmdError::InvalidArgument(ref name, ref msg)) => {
+                assert_eq!(name, "lang");
+                assert_eq!(msg, "Expected to be one of `en`, `de` but was `fr`");
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        let res = json!({ "error": "type", "type": "text", "expected": "a checkbox" });
+        match control_result("agree", res) {
+            Err(error::CmdError::InvalidArgument(_, ref msg)) => {
+                assert_eq!(msg, "Expected to be a checkbox but was `text`");
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        match control_result("a", json!(true)) {
+            Err(error::CmdError::NotW3C(..)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn fill_results() {
+        let res = json!([["a", { "error": "missing" }], ["b", { "error": "missing" }]]);
+        let errors = fill_errors(res).unwrap();
+        let names: Vec<_> = errors.iter().map(|&(ref name, _)| &**name).collect();
+        assert_eq!(names, vec!["a", "b"]);
+
+        assert!(fill_errors(json!([])).unwrap().is_empty());
+        assert!(fill_errors(json!([["a", null]])).is_err());
+        assert!(fill_errors(json!({})).is_err());
+    }
+
+    #[test]
+    fn prepared_fields() {
+        let (ok, errors) = prepare_fields(vec![
+            ("name".to_string(), json!("Jane")),
+            ("skipped".to_string(), Value::Null),
+            ("age".to_string(), json!(42)),
+            ("agree".to_string(), json!(true)),
+            ("tags".to_string(), json!(["a", "b"])),
+            ("bad_tags".to_string(), json!(["a", 1])),
+            ("nested".to_string(), json!({ "a": 1 })),
+        ]);
+        assert_eq!(
+            ok,
+            vec![
+                ("name".to_string(), json!("Jane")),
+                ("age".to_string(), json!("42")),
+                ("agree".to_string(), json!(true)),
+                ("tags".to_string(), json!(["a", "b"])),
+            ]
+        );
+        let names: Vec<_> = errors.iter().map(|&(ref name, _)| &**name).collect();
+        assert_eq!(names, vec!["bad_tags", "nested"]);
+    }
+
+    #[test]
+    fn errors_in_field_order() {
+        let order = field_names(&[
+            ("a".to_string(), json!("x")),
+            ("b".to_string(), json!({})),
+            ("c".to_string(), json!("y")),
+        ]);
+        let mut errors = vec![
+            (
+                "b".to_string(),
+                error::CmdError::NoSuchField("b".to_string()),
+            ),
+            (
+                "c".to_string(),
+                error::CmdError::NoSuchField("c".to_string()),
+            ),
+            (
+                "a".to_string(),
+                error::CmdError::NoSuchField("a".to_string()),
+            ),
+        ];
+        in_field_order(&order, &mut errors);
+        let names: Vec<_> = errors.iter().map(|&(ref name, _)| &**name).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+}
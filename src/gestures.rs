@@ -0,0 +1,84 @@
+/// The script used by `Element::scroll_into_view`, and before every gesture.
+///
+/// Pointer actions that target an element outside the viewport fail with "move target out of
+/// bounds", so the element is centered first.
+pub(crate) const SCROLL_INTO_VIEW: &str = "\
+    var el = arguments[0];\
+    try {\
+        el.scrollIntoView({ block: 'center', inline: 'center' });\
+    } catch (e) {\
+        el.scrollIntoView(true);\
+    }";
+
+/// The script that synthesizes a gesture with DOM events, for drivers without input actions.
+///
+/// It is called with the element, the name of the gesture (`hover`, `click`, `dblclick`,
+/// `contextmenu`, or `drag`), the offset from the center of the element, and, for `drag`, the
+/// element to drop onto. Events are dispatched to whatever is at the target point, like a real
+/// pointer would, and draggable elements also get the HTML5 drag-and-drop events.
+pub(crate) const SYNTHESIZE: &str = "\
+    var el = arguments[0], gesture = arguments[1], dx = arguments[2], dy = arguments[3];\
+    var dest = arguments[4];\
+    var center = function(e, ox, oy) {\
+        var r = e.getBoundingClientRect();\
+        return { x: r.left + r.width / 2 + ox, y: r.top + r.height / 2 + oy };\
+    };\
+    var at = function(p, fallback) { return document.elementFromPoint(p.x, p.y) || fallback; };\
+    var fire = function(type, target, p, button, detail, extra) {\
+        var init = {\
+            bubbles: type !== 'mouseenter' && type !== 'mouseleave', cancelable: true, view: window,\
+            clientX: p.x, clientY: p.y, screenX: p.x, screenY: p.y,\
+            button: button, buttons: type === 'mousedown' ? 1 << [0, 2, 1][button] : 0, detail: detail\
+        };\
+        var ev;\
+        if (extra) {\
+            try { ev = new DragEvent(type, init); } catch (e) { ev = new MouseEvent(type, init); }\
+            if (!ev.dataTransfer) { Object.defineProperty(ev, 'dataTransfer', { value: extra }); }\
+        } else {\
+            ev = new MouseEvent(type, init);\
+        }\
+        return target.dispatchEvent(ev);\
+    };\
+    var hover = function(target, p) {\
+        fire('mouseover', target, p, 0, 0);\
+        fire('mouseenter', target, p, 0, 0);\
+        fire('mousemove', target, p, 0, 0);\
+    };\
+    var press = function(target, p, button, detail) {\
+        if (fire('mousedown', target, p, button, detail) && button === 0 && target.focus) {\
+            target.focus();\
+        }\
+    };\
+    var click = function(target, p, button, detail) {\
+        press(target, p, button, detail);\
+        fire('mouseup', target, p, button, detail);\
+        fire(button === 2 ? 'contextmenu' : button === 0 ? 'click' : 'auxclick', target, p, button, detail);\
+    };\
+    var p = center(el, dx, dy), target = at(p, el);\
+    hover(target, p);\
+    if (gesture === 'click') {\
+        click(target, p, 0, 1);\
+    } else if (gesture === 'dblclick') {\
+        click(target, p, 0, 1);\
+        click(target, p, 0, 2);\
+        fire('dblclick', target, p, 0, 2);\
+    } else if (gesture === 'contextmenu') {\
+        click(target, p, 2, 1);\
+    } else if (gesture === 'drag') {\
+        press(target, p, 0, 1);\
+        var q = center(dest, 0, 0), over = at(q, dest);\
+        if (el.draggable) {\
+            var dt = null;\
+            try { dt = new DataTransfer(); } catch (e) {}\
+            dt = dt || { data: {}, setData: function(k, v) { this.data[k] = v; },\
+                         getData: function(k) { return this.data[k]; },\
+                         dropEffect: 'move', effectAllowed: 'all', types: [], files: [] };\
+            fire('dragstart', el, p, 0, 0, dt);\
+            fire('dragenter', over, q, 0, 0, dt);\
+            if (!fire('dragover', over, q, 0, 0, dt)) { fire('drop', over, q, 0, 0, dt); }\
+            fire('dragend', el, q, 0, 0, dt);\
+        } else {\
+            hover(over, q);\
+            fire('mouseup', over, q, 0, 1);\
+        }\
+    }";
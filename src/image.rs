@@ -0,0 +1,101 @@
+use error::ImageError;
+use png::{self, HasParameters};
+
+/// A decoded image with 8-bit RGBA pixels, stored row by row.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct Image {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) data: Vec<u8>,
+}
+
+impl Image {
+    /// Create a new, fully transparent image of the given size.
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Decode a PNG image.
+    ///
+    /// Whatever the color type of the PNG, the decoded image is converted to 8-bit RGBA.
+    pub(crate) fn decode(png: &[u8]) -> Result<Self, ImageError> {
+        let mut decoder = png::Decoder::new(png);
+        decoder.set(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info().map_err(ImageError::Decode)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).map_err(ImageError::Decode)?;
+
+        let pixels = info.width as usize * info.height as usize;
+        let data = match info.color_type {
+            png::ColorType::RGBA => buf,
+            png::ColorType::RGB => {
+                let mut data = Vec::with_capacity(pixels * 4);
+                for p in buf.chunks(3) {
+                    data.extend_from_slice(p);
+                    data.push(0xff);
+                }
+                data
+            }
+            png::ColorType::GrayscaleAlpha => {
+                let mut data = Vec::with_capacity(pixels * 4);
+                for p in buf.chunks(2) {
+                    data.extend_from_slice(&[p[0], p[0], p[0], p[1]]);
+                }
+                data
+            }
+            png::ColorType::Grayscale => {
+                let mut data = Vec::with_capacity(pixels * 4);
+                for &p in &buf {
+                    data.extend_from_slice(&[p, p, p, 0xff]);
+                }
+                data
+            }
+            png::ColorType::Indexed => {
+                // EXPAND should have turned this into RGB(A)
+                return Err(ImageError::Unsupported(format!("{:?}", info.color_type)));
+            }
+        };
+
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+
+    /// Encode this image as an RGBA PNG.
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, ImageError> {
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+            encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(ImageError::Encode)?;
+            writer
+                .write_image_data(&self.data)
+                .map_err(ImageError::Encode)?;
+        }
+        Ok(png)
+    }
+
+    /// Copy the first `rows` rows of `src` into this image, starting at row `y`.
+    ///
+    /// Any part of `src` that falls outside of this image is ignored.
+    pub(crate) fn blit_rows(&mut self, src: &Image, y: u32, rows: u32) {
+        let width = ::std::cmp::min(self.width, src.width) as usize * 4;
+        let rows = ::std::cmp::min(rows, src.height);
+        for row in 0..rows {
+            let dst_row = y + row;
+            if dst_row >= self.height {
+                break;
+            }
+
+            let from = row as usize * src.width as usize * 4;
+            let to = dst_row as usize * self.width as usize * 4;
+            self.data[to..to + width].copy_from_slice(&src.data[from..from + width]);
+        }
+    }
+}
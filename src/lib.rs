@@ -187,18 +187,26 @@
 //! [`geckodriver`]: https://github.com/mozilla/geckodriver
 #![deny(missing_docs)]
 
+extern crate base64;
 extern crate futures;
 extern crate hyper;
 extern crate hyper_tls;
+extern crate png;
 extern crate rustc_serialize;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_path_to_error;
 extern crate tokio_core;
 extern crate url;
 extern crate webdriver;
+extern crate zip;
 
 use futures::{future, Future, Stream};
-use rustc_serialize::json::Json;
-use std::cell::RefCell;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use webdriver::command::WebDriverCommand;
 use webdriver::common::ELEMENT_KEY;
 use webdriver::error::ErrorStatus;
@@ -209,6 +217,46 @@ pub use hyper::Method;
 /// Error types.
 pub mod error;
 
+pub mod compat;
+
+mod actions;
+pub use actions::{Actions, Key, MouseButton, PointerType};
+
+mod cookies;
+pub use cookies::{Cookie, SameSite};
+
+mod download;
+pub use download::Download;
+
+mod form;
+pub use form::{FormControl, InvalidControl};
+
+mod gestures;
+
+mod image;
+
+mod logs;
+pub use logs::{LogEntry, LogLevel, LogSource};
+
+mod navigation;
+pub use navigation::LoadState;
+
+mod preload;
+pub use preload::PreloadScript;
+
+mod print;
+pub use print::{PrintMargins, PrintOptions, PrintOrientation, PrintPageSize};
+
+mod script;
+pub use script::ScriptValue;
+
+mod select;
+pub use select::{Select, SelectOption};
+
+mod upload;
+
+pub mod visual;
+
 /// An element locator.
 ///
 /// See <https://www.w3.org/TR/webdriver/#element-retrieval>.
@@ -252,7 +300,104 @@ impl<'a> Into<webdriver::command::LocatorParameters> for Locator<'a> {
     }
 }
 
-type Cmd = WebDriverCommand<webdriver::command::VoidWebDriverExtensionCommand>;
+/// A WebDriver command that the `webdriver` crate does not (yet) know how to express.
+///
+/// The command is issued with the given `method` to `path`, relative to the current session.
+#[derive(Clone, Debug, PartialEq)]
+struct RawCmd {
+    method: Method,
+    path: String,
+    body: Option<Value>,
+}
+
+impl webdriver::command::WebDriverExtensionCommand for RawCmd {
+    fn parameters_json(&self) -> Option<rustc_serialize::json::Json> {
+        // only used when the webdriver crate serializes a command itself, which we never have it
+        // do: issue_wd_cmd sends `body` as it is
+        None
+    }
+}
+
+type Cmd = WebDriverCommand<RawCmd>;
+
+/// Encode the parameters of a command from the `webdriver` crate as JSON.
+///
+/// The `webdriver` crate only knows how to encode its types with `rustc_serialize`, so this is
+/// where they cross over to `serde_json`.
+fn wd_json<T: rustc_serialize::json::ToJson>(params: &T) -> Value {
+    compat::from_rustc(&params.to_json())
+}
+
+/// Convert capabilities into the form the `webdriver` crate expects.
+fn wd_capabilities(cap: &serde_json::Map<String, Value>) -> webdriver::capabilities::Capabilities {
+    cap.iter()
+        .map(|(k, v)| (k.clone(), compat::to_rustc(v)))
+        .collect()
+}
+
+/// Serialize a web element reference for use as a script argument.
+///
+/// Legacy drivers expect a different key; `Client::fixup_elements` takes care of that.
+fn element_ref(e: &webdriver::common::WebElement) -> Value {
+    let mut o = serde_json::Map::new();
+    o.insert(ELEMENT_KEY.to_string(), Value::String(e.id.clone()));
+    Value::Object(o)
+}
+
+/// How long navigation commands wait for the new page to load.
+///
+/// Pass this as the `pageLoadStrategy` capability to `Client::with_capabilities` to set it for the
+/// whole session, or to `Client::navigate_with` to wait that long for a single navigation. See
+/// <https://www.w3.org/TR/webdriver/#dfn-table-of-page-load-strategies>.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum PageLoadStrategy {
+    /// Do not wait for the page at all.
+    None,
+    /// Wait until the HTML has been parsed (`DOMContentLoaded`).
+    Eager,
+    /// Wait until the page and all its resources have loaded (`load`). This is the default.
+    Normal,
+}
+
+impl serde::Serialize for PageLoadStrategy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match *self {
+            PageLoadStrategy::None => "none",
+            PageLoadStrategy::Eager => "eager",
+            PageLoadStrategy::Normal => "normal",
+        })
+    }
+}
+
+/// How the browser should handle user prompts that appear while other commands are executing.
+///
+/// Pass this as the `unhandledPromptBehavior` capability to `Client::with_capabilities`. See
+/// <https://www.w3.org/TR/webdriver/#dfn-unhandled-prompt-behavior>.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum UnhandledPromptBehavior {
+    /// Dismiss the prompt.
+    Dismiss,
+    /// Accept the prompt.
+    Accept,
+    /// Dismiss the prompt, and make the command fail with "unexpected alert open".
+    DismissAndNotify,
+    /// Accept the prompt, and make the command fail with "unexpected alert open".
+    AcceptAndNotify,
+    /// Leave the prompt open, and make the command fail with "unexpected alert open".
+    Ignore,
+}
+
+impl serde::Serialize for UnhandledPromptBehavior {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match *self {
+            UnhandledPromptBehavior::Dismiss => "dismiss",
+            UnhandledPromptBehavior::Accept => "accept",
+            UnhandledPromptBehavior::DismissAndNotify => "dismiss and notify",
+            UnhandledPromptBehavior::AcceptAndNotify => "accept and notify",
+            UnhandledPromptBehavior::Ignore => "ignore",
+        })
+    }
+}
 
 /// State held by a `Client`
 struct Inner {
@@ -262,6 +407,9 @@ struct Inner {
     session: RefCell<Option<String>>,
     legacy: bool,
     ua: RefCell<Option<String>>,
+    browser: RefCell<Option<String>>,
+    preload: RefCell<preload::Registry>,
+    remote_uploads: Cell<Option<bool>>,
 }
 
 impl Inner {
@@ -311,6 +459,7 @@ pub struct Element {
 pub struct Form {
     c: Client,
     f: webdriver::common::WebElement,
+    validate: bool,
 }
 
 impl Client {
@@ -328,19 +477,29 @@ impl Client {
         // https://www.w3.org/TR/webdriver/#dfn-new-session
         self.issue_wd_cmd(WebDriverCommand::NewSession(params))
             .then(move |r| match r {
-                Ok((this, Json::Object(mut v))) => {
+                Ok((this, Value::Object(mut v))) => {
                     // TODO: not all impls are w3c compatible
                     // See https://github.com/SeleniumHQ/selenium/blob/242d64ca4cd3523489ac1e58703fd7acd4f10c5a/py/selenium/webdriver/remote/webdriver.py#L189
                     // and https://github.com/SeleniumHQ/selenium/blob/242d64ca4cd3523489ac1e58703fd7acd4f10c5a/py/selenium/webdriver/remote/webdriver.py#L200
+                    //
+                    // remember which browser we got, since some features are browser-specific
+                    let browser = v
+                        .get("capabilities")
+                        .or_else(|| v.get("value"))
+                        .and_then(|caps| caps.get("browserName"))
+                        .and_then(|name| name.as_str())
+                        .map(|name| name.to_lowercase());
+                    *this.0.browser.borrow_mut() = browser;
+
                     if let Some(session_id) = v.remove("sessionId") {
-                        if let Some(session_id) = session_id.as_string() {
+                        if let Some(session_id) = session_id.as_str() {
                             *this.0.session.borrow_mut() = Some(session_id.to_string());
                             return Ok(this);
                         }
                         v.insert("sessionId".to_string(), session_id);
-                        Err(error::NewSessionError::NotW3C(Json::Object(v)))
+                        Err(error::NewSessionError::NotW3C(Value::Object(v)))
                     } else {
-                        Err(error::NewSessionError::NotW3C(Json::Object(v)))
+                        Err(error::NewSessionError::NotW3C(Value::Object(v)))
                     }
                 }
                 Ok((_, v)) | Err(error::CmdError::NotW3C(v)) => {
@@ -349,7 +508,7 @@ impl Client {
                 Err(error::CmdError::Failed(e)) => Err(error::NewSessionError::Failed(e)),
                 Err(error::CmdError::Lost(e)) => Err(error::NewSessionError::Lost(e)),
                 Err(error::CmdError::NotJson(v)) => {
-                    Err(error::NewSessionError::NotW3C(Json::String(v)))
+                    Err(error::NewSessionError::NotW3C(Value::String(v)))
                 }
                 Err(error::CmdError::Standard(
                     e @ WebDriverError {
@@ -378,6 +537,41 @@ impl Client {
     pub fn new(
         webdriver: &str,
         handle: &tokio_core::reactor::Handle,
+    ) -> impl Future<Item = Self, Error = error::NewSessionError> + 'static {
+        Self::with_capabilities(webdriver, handle, serde_json::Map::new())
+    }
+
+    /// Create a new `Client` like `Client::new`, but also request the given [capabilities] for the
+    /// new session.
+    ///
+    /// For example, to have the browser automatically accept any user prompts (like `alert()`)
+    /// that would otherwise interrupt subsequent commands:
+    ///
+    /// ```no_run
+    /// # extern crate tokio_core;
+    /// # extern crate serde_json;
+    /// # extern crate fantoccini;
+    /// # fn main() {
+    /// use fantoccini::{Client, UnhandledPromptBehavior};
+    /// let mut core = tokio_core::reactor::Core::new().unwrap();
+    /// let mut caps = serde_json::Map::new();
+    /// caps.insert(
+    ///     "unhandledPromptBehavior".to_string(),
+    ///     serde_json::to_value(UnhandledPromptBehavior::Accept).unwrap(),
+    /// );
+    /// let c = Client::with_capabilities("http://localhost:4444", &core.handle(), caps);
+    /// let c = core.run(c).unwrap();
+    /// # }
+    /// ```
+    ///
+    /// Unless `pageLoadStrategy` is given, `normal` is requested, so that navigation waits for
+    /// pages to load.
+    ///
+    /// [capabilities]: https://www.w3.org/TR/webdriver/#capabilities
+    pub fn with_capabilities(
+        webdriver: &str,
+        handle: &tokio_core::reactor::Handle,
+        mut cap: serde_json::Map<String, Value>,
     ) -> impl Future<Item = Self, Error = error::NewSessionError> + 'static {
         // Where is the WebDriver server?
         let wdb = match webdriver.parse::<url::Url>() {
@@ -400,19 +594,19 @@ impl Client {
             session: RefCell::new(None),
             legacy: false,
             ua: RefCell::new(None),
+            browser: RefCell::new(None),
+            preload: RefCell::new(preload::Registry::default()),
+            remote_uploads: Cell::new(None),
         }));
 
         // Required capabilities
         // https://www.w3.org/TR/webdriver/#capabilities
-        let mut cap = webdriver::capabilities::Capabilities::new();
         //  - we want the browser to wait for the page to load
-        cap.insert(
-            "pageLoadStrategy".to_string(),
-            Json::String("normal".to_string()),
-        );
+        cap.entry("pageLoadStrategy".to_string())
+            .or_insert_with(|| Value::String("normal".to_string()));
 
         let session_config = webdriver::capabilities::SpecNewSessionParameters {
-            alwaysMatch: cap.clone(),
+            alwaysMatch: wd_capabilities(&cap),
             firstMatch: vec![],
         };
         let spec = webdriver::command::NewSessionParameters::Spec(session_config);
@@ -422,13 +616,13 @@ impl Client {
                 error::NewSessionError::NotW3C(json) => {
                     let mut legacy = false;
                     match json {
-                        Json::String(ref err) if err.starts_with("Missing Command Parameter") => {
+                        Value::String(ref err) if err.starts_with("Missing Command Parameter") => {
                             // ghostdriver
                             legacy = true;
                         }
-                        Json::Object(ref err) => {
+                        Value::Object(ref err) => {
                             legacy = err.get("message")
-                                .and_then(|m| m.as_string())
+                                .and_then(|m| m.as_str())
                                 .map(|s| {
                                     // chromedriver < 2.29 || chromedriver == 2.29
                                     s.contains("cannot find dict 'desiredCapabilities'")
@@ -443,8 +637,19 @@ impl Client {
                         // we're dealing with an implementation that only supports the legacy
                         // WebDriver protocol:
                         // https://github.com/SeleniumHQ/selenium/wiki/JsonWireProtocol
+                        let mut cap = cap;
+                        if let Some(b) = cap.remove("unhandledPromptBehavior") {
+                            // the legacy name only knows about accept, dismiss, and ignore
+                            let b = b.as_str().map(|b| b.split(' ').next().unwrap().to_string());
+                            if let Some(b) = b {
+                                cap.insert(
+                                    "unexpectedAlertBehaviour".to_string(),
+                                    Value::String(b),
+                                );
+                            }
+                        }
                         let session_config = webdriver::capabilities::LegacyNewSessionParameters {
-                            required: cap,
+                            required: wd_capabilities(&cap),
                             desired: webdriver::capabilities::Capabilities::new(),
                         };
                         let spec = webdriver::command::NewSessionParameters::Legacy(session_config);
@@ -490,12 +695,20 @@ impl Client {
             WebDriverCommand::DeleteSession => unreachable!(),
             WebDriverCommand::Get(..) | WebDriverCommand::GetCurrentUrl => base.join("url"),
             WebDriverCommand::GoBack => base.join("back"),
+            WebDriverCommand::GoForward => base.join("forward"),
             WebDriverCommand::Refresh => base.join("refresh"),
+            WebDriverCommand::GetTitle => base.join("title"),
             WebDriverCommand::GetPageSource => base.join("source"),
             WebDriverCommand::FindElement(..) => base.join("element"),
-            WebDriverCommand::GetCookies => base.join("cookie"),
-            WebDriverCommand::ExecuteScript(..) if self.0.legacy => base.join("execute"),
-            WebDriverCommand::ExecuteScript(..) => base.join("execute/sync"),
+            WebDriverCommand::GetCookies | WebDriverCommand::DeleteCookies => base.join("cookie"),
+            WebDriverCommand::GetNamedCookie(ref name)
+            | WebDriverCommand::DeleteCookie(ref name) => {
+                use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+                base.join(&format!(
+                    "cookie/{}",
+                    utf8_percent_encode(name, PATH_SEGMENT_ENCODE_SET)
+                ))
+            }
             WebDriverCommand::GetElementProperty(ref we, ref prop) => {
                 base.join(&format!("element/{}/property/{}", we.id, prop))
             }
@@ -514,24 +727,38 @@ impl Client {
             WebDriverCommand::ElementSendKeys(ref we, _) => {
                 base.join(&format!("element/{}/value", we.id))
             }
+            WebDriverCommand::TakeScreenshot => base.join("screenshot"),
+            WebDriverCommand::TakeElementScreenshot(ref we) => {
+                base.join(&format!("element/{}/screenshot", we.id))
+            }
             WebDriverCommand::SetWindowRect(..) => base.join("window/rect"),
             WebDriverCommand::GetWindowRect => base.join("window/rect"),
+            WebDriverCommand::GetAlertText | WebDriverCommand::SendAlertText(..)
+                if self.0.legacy =>
+            {
+                base.join("alert_text")
+            }
+            WebDriverCommand::GetAlertText | WebDriverCommand::SendAlertText(..) => {
+                base.join("alert/text")
+            }
+            WebDriverCommand::AcceptAlert if self.0.legacy => base.join("accept_alert"),
+            WebDriverCommand::AcceptAlert => base.join("alert/accept"),
+            WebDriverCommand::DismissAlert if self.0.legacy => base.join("dismiss_alert"),
+            WebDriverCommand::DismissAlert => base.join("alert/dismiss"),
+            WebDriverCommand::ReleaseActions => base.join("actions"),
+            WebDriverCommand::Extension(ref raw) => base.join(&raw.path),
             _ => unimplemented!(),
         }
     }
 
     /// Helper for issuing a WebDriver command, and then reading and parsing the response.
     ///
-    /// Since most `WebDriverCommand` arguments already implement `ToJson`, this is mostly a matter
-    /// of picking the right URL and method from [the spec], and stuffing the JSON encoded
-    /// arguments (if any) into the body.
+    /// This is mostly a matter of picking the right URL and method from [the spec], and stuffing
+    /// the JSON encoded arguments (if any) into the body. Arguments of the `webdriver` crate's
+    /// types are converted with `wd_json`.
     ///
     /// [the spec]: https://www.w3.org/TR/webdriver/#list-of-endpoints
-    fn issue_wd_cmd(
-        self,
-        cmd: WebDriverCommand<webdriver::command::VoidWebDriverExtensionCommand>,
-    ) -> impl Future<Item = (Self, Json), Error = error::CmdError> {
-        use rustc_serialize::json::ToJson;
+    fn issue_wd_cmd(self, cmd: Cmd) -> impl Future<Item = (Self, Value), Error = error::CmdError> {
         use webdriver::command;
 
         // most actions are just get requests with not parameters
@@ -545,40 +772,49 @@ impl Client {
         // but some are special
         match cmd {
             WebDriverCommand::NewSession(command::NewSessionParameters::Spec(ref conf)) => {
-                body = Some(format!("{}", conf.to_json()));
+                body = Some(wd_json(conf));
                 method = Method::Post;
             }
             WebDriverCommand::NewSession(command::NewSessionParameters::Legacy(ref conf)) => {
-                body = Some(format!("{}", conf.to_json()));
+                body = Some(wd_json(conf));
                 method = Method::Post;
             }
             WebDriverCommand::Get(ref params) => {
-                body = Some(format!("{}", params.to_json()));
+                body = Some(wd_json(params));
                 method = Method::Post;
             }
             WebDriverCommand::FindElement(ref loc)
             | WebDriverCommand::FindElementElement(_, ref loc) => {
-                body = Some(format!("{}", loc.to_json()));
-                method = Method::Post;
-            }
-            WebDriverCommand::ExecuteScript(ref script) => {
-                body = Some(format!("{}", script.to_json()));
+                body = Some(wd_json(loc));
                 method = Method::Post;
             }
-            WebDriverCommand::ElementSendKeys(_, ref keys) => {
-                body = Some(format!("{}", keys.to_json()));
+            WebDriverCommand::ElementSendKeys(_, ref keys)
+            | WebDriverCommand::SendAlertText(ref keys) => {
+                body = Some(wd_json(keys));
                 method = Method::Post;
             }
             WebDriverCommand::ElementClick(..)
             | WebDriverCommand::GoBack
-            | WebDriverCommand::Refresh => {
-                body = Some("{}".to_string());
+            | WebDriverCommand::GoForward
+            | WebDriverCommand::Refresh
+            | WebDriverCommand::AcceptAlert
+            | WebDriverCommand::DismissAlert => {
+                body = Some(json!({}));
                 method = Method::Post;
             }
             WebDriverCommand::SetWindowRect(ref params) => {
-                body = Some(format!("{}", params.to_json()));
+                body = Some(wd_json(params));
                 method = Method::Post;
             }
+            WebDriverCommand::DeleteCookie(..)
+            | WebDriverCommand::DeleteCookies
+            | WebDriverCommand::ReleaseActions => {
+                method = Method::Delete;
+            }
+            WebDriverCommand::Extension(ref raw) => {
+                body = raw.body.clone();
+                method = raw.method.clone();
+            }
             _ => {}
         }
 
@@ -588,11 +824,12 @@ impl Client {
             req.headers_mut()
                 .set(hyper::header::UserAgent::new(s.to_owned()));
         }
-        if let Some(ref body) = body {
+        if let Some(body) = body {
+            let body = body.to_string();
             req.headers_mut().set(hyper::header::ContentType::json());
             req.headers_mut()
                 .set(hyper::header::ContentLength(body.len() as u64));
-            req.set_body(body.clone());
+            req.set_body(body);
         }
 
         let req = self.0.c.request(req);
@@ -639,8 +876,8 @@ impl Client {
 
                 // https://www.w3.org/TR/webdriver/#dfn-send-a-response
                 // NOTE: the standard specifies that even errors use the "Send a Reponse" steps
-                let body = match Json::from_str(&*body)? {
-                    Json::Object(mut v) => {
+                let body = match serde_json::from_str(&*body)? {
+                    Value::Object(mut v) => {
                         if this.0.legacy {
                             legacy_status = v["status"].as_u64().unwrap();
                             is_success = legacy_status == 0;
@@ -648,10 +885,10 @@ impl Client {
 
                         if this.0.legacy && is_new_session {
                             // legacy implementations do not wrap sessionId inside "value"
-                            Ok(Json::Object(v))
+                            Ok(Value::Object(v))
                         } else {
                             v.remove("value")
-                                .ok_or_else(|| error::CmdError::NotW3C(Json::Object(v)))
+                                .ok_or_else(|| error::CmdError::NotW3C(Value::Object(v)))
                         }
                     }
                     v => Err(error::CmdError::NotW3C(v)),
@@ -663,10 +900,10 @@ impl Client {
 
                 // https://www.w3.org/TR/webdriver/#dfn-send-an-error
                 // https://www.w3.org/TR/webdriver/#handling-errors
-                if !body.is_object() {
-                    return Err(error::CmdError::NotW3C(body));
-                }
-                let mut body = body.into_object().unwrap();
+                let mut body = match body {
+                    Value::Object(body) => body,
+                    body => return Err(error::CmdError::NotW3C(body)),
+                };
 
                 // phantomjs injects a *huge* field with the entire screen contents -- remove that
                 body.remove("screen");
@@ -675,7 +912,7 @@ impl Client {
                     // old clients use status codes instead of "error", and we now have to map them
                     // https://github.com/SeleniumHQ/selenium/wiki/JsonWireProtocol#response-status-codes
                     if !body.contains_key("message") || !body["message"].is_string() {
-                        return Err(error::CmdError::NotW3C(Json::Object(body)));
+                        return Err(error::CmdError::NotW3C(Value::Object(body)));
                     }
                     match legacy_status {
                         6 | 33 => ErrorStatus::SessionNotCreated,
@@ -698,18 +935,18 @@ impl Client {
                         28 => ErrorStatus::ScriptTimeout,
                         29 => ErrorStatus::InvalidCoordinates,
                         34 => ErrorStatus::MoveTargetOutOfBounds,
-                        _ => return Err(error::CmdError::NotW3C(Json::Object(body))),
+                        _ => return Err(error::CmdError::NotW3C(Value::Object(body))),
                     }
                 } else {
                     if !body.contains_key("error") || !body.contains_key("message")
                         || !body["error"].is_string()
                         || !body["message"].is_string()
                     {
-                        return Err(error::CmdError::NotW3C(Json::Object(body)));
+                        return Err(error::CmdError::NotW3C(Value::Object(body)));
                     }
 
                     use hyper::StatusCode;
-                    let error = body["error"].as_string().unwrap();
+                    let error = body["error"].as_str().unwrap();
                     match status {
                         StatusCode::BadRequest => match error {
                             "element click intercepted" => ErrorStatus::ElementClickIntercepted,
@@ -729,6 +966,7 @@ impl Client {
                         },
                         StatusCode::NotFound => match error {
                             "unknown command" => ErrorStatus::UnknownCommand,
+                            "no such alert" => ErrorStatus::NoSuchAlert,
                             "no such cookie" => ErrorStatus::NoSuchCookie,
                             "invalid session id" => ErrorStatus::InvalidSessionId,
                             "no such element" => ErrorStatus::NoSuchElement,
@@ -758,7 +996,7 @@ impl Client {
                     }
                 };
 
-                let message = body["message"].as_string().unwrap().to_string();
+                let message = body["message"].as_str().unwrap().to_string();
                 Err(error::CmdError::from(WebDriverError::new(es, message)))
             });
 
@@ -838,25 +1076,25 @@ impl Client {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::GetWindowRect)
             .and_then(|(_, v)| match v {
-                Json::Object(mut obj) => {
+                Value::Object(mut obj) => {
                     let x = match obj.remove("x").and_then(|x| x.as_u64()) {
                         Some(x) => x,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     let y = match obj.remove("y").and_then(|y| y.as_u64()) {
                         Some(y) => y,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     let width = match obj.remove("width").and_then(|width| width.as_u64()) {
                         Some(width) => width,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     let height = match obj.remove("height").and_then(|height| height.as_u64()) {
                         Some(height) => height,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     Ok((x, y, width, height))
@@ -904,15 +1142,15 @@ impl Client {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::GetWindowRect)
             .and_then(|(_, v)| match v {
-                Json::Object(mut obj) => {
+                Value::Object(mut obj) => {
                     let width = match obj.remove("width").and_then(|width| width.as_u64()) {
                         Some(width) => width,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     let height = match obj.remove("height").and_then(|height| height.as_u64()) {
                         Some(height) => height,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     Ok((width, height))
@@ -960,15 +1198,15 @@ impl Client {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::GetWindowRect)
             .and_then(|(_, v)| match v {
-                Json::Object(mut obj) => {
+                Value::Object(mut obj) => {
                     let x = match obj.remove("x").and_then(|x| x.as_u64()) {
                         Some(x) => x,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     let y = match obj.remove("y").and_then(|y| y.as_u64()) {
                         Some(y) => y,
-                        None => return Err(error::CmdError::NotW3C(Json::Object(obj))),
+                        None => return Err(error::CmdError::NotW3C(Value::Object(obj))),
                     };
 
                     Ok((x, y))
@@ -987,7 +1225,7 @@ impl Client {
                     url: url.into_string(),
                 }))
             })
-            .map(|(this, _)| this)
+            .and_then(|(this, _)| this.run_preloads())
     }
 
     fn current_url_(
@@ -996,7 +1234,7 @@ impl Client {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::GetCurrentUrl)
             .and_then(|(this, url)| {
-                if let Some(url) = url.as_string() {
+                if let Some(url) = url.as_str() {
                     return Ok((this, url.parse()?));
                 }
 
@@ -1014,7 +1252,7 @@ impl Client {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::GetPageSource)
             .and_then(|(_, src)| {
-                if let Some(src) = src.as_string() {
+                if let Some(src) = src.as_str() {
                     return Ok(src.to_string());
                 }
 
@@ -1026,35 +1264,530 @@ impl Client {
     pub fn back(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::GoBack)
-            .map(|(this, _)| this)
+            .and_then(|(this, _)| this.run_preloads())
+    }
+
+    /// Go forward to the next page.
+    pub fn forward(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::GoForward)
+            .and_then(|(this, _)| this.run_preloads())
     }
 
     /// Refresh the current previous page.
     pub fn refresh(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
         self.dup()
             .issue_wd_cmd(WebDriverCommand::Refresh)
-            .map(|(this, _)| this)
+            .and_then(|(this, _)| this.run_preloads())
+    }
+
+    /// Get the title of the current page.
+    pub fn title(&self) -> impl Future<Item = String, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::GetTitle)
+            .and_then(|(_, title)| {
+                if let Some(title) = title.as_str() {
+                    return Ok(title.to_string());
+                }
+
+                Err(error::CmdError::NotW3C(title))
+            })
+    }
+
+    /// Get a PNG-encoded screenshot of the current page.
+    ///
+    /// Note that only the currently visible portion of the page (the viewport) is captured.
+    pub fn screenshot(&self) -> impl Future<Item = Vec<u8>, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::TakeScreenshot)
+            .and_then(|(_, src)| Self::decode_base64(src))
+    }
+
+    /// Take a screenshot of the current page, and save it as a PNG file at `path`.
+    ///
+    /// The file is written synchronously once the screenshot arrives, which blocks the event loop
+    /// for as long as the write takes.
+    pub fn save_screenshot<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let path = path.as_ref().to_path_buf();
+        let this = self.dup();
+        self.screenshot().and_then(move |png| {
+            use std::io::Write;
+            std::fs::File::create(&path)
+                .and_then(|mut f| f.write_all(&png))
+                .map_err(error::CmdError::FileSystem)?;
+            Ok(this)
+        })
+    }
+
+    /// Get a PNG-encoded screenshot of the entire current page, not just the visible viewport.
+    ///
+    /// If the browser supports this natively (currently only Firefox), the driver is asked for it
+    /// directly. Otherwise, the page is scrolled through from top to bottom, and screenshots of
+    /// each viewport are stitched together. In that case, elements with `fixed` or `sticky`
+    /// positioning (such as navigation headers) are hidden after the first viewport so that they
+    /// do not repeat down the page, and the page's `devicePixelRatio` is taken into account. Any
+    /// hidden elements, as well as the scroll position, are restored afterwards.
+    pub fn full_page_screenshot(
+        &self,
+    ) -> impl Future<Item = Vec<u8>, Error = error::CmdError> + 'static {
+        let is_firefox = self
+            .0
+            .browser
+            .borrow()
+            .as_ref()
+            .map(|b| b == "firefox")
+            .unwrap_or(false);
+        if self.0.legacy || !is_firefox {
+            return future::Either::B(self.stitch_screenshot());
+        }
+
+        let cmd = RawCmd {
+            method: Method::Get,
+            path: "moz/screenshot/full".to_string(),
+            body: None,
+        };
+        let this = self.dup();
+        future::Either::A(
+            self.dup()
+                .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+                .and_then(|(_, src)| Self::decode_base64(src))
+                .or_else(move |e| match e {
+                    // older geckodrivers do not have the endpoint
+                    error::CmdError::Standard(WebDriverError {
+                        error: ErrorStatus::UnknownCommand,
+                        ..
+                    }) => future::Either::A(this.stitch_screenshot()),
+                    e => future::Either::B(future::err(e)),
+                }),
+        )
+    }
+
+    /// Print the current page to PDF, and return the bytes of the PDF document.
+    ///
+    /// Not all drivers support printing. Some, such as chromedriver, only do so in headless mode.
+    pub fn print(
+        &self,
+        options: PrintOptions,
+    ) -> impl Future<Item = Vec<u8>, Error = error::CmdError> + 'static {
+        if let Err(e) = options.validate() {
+            return future::Either::A(future::err(e));
+        }
+
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: "print".to_string(),
+            body: Some(options.to_json()),
+        };
+        future::Either::B(
+            self.dup()
+                .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+                .and_then(|(_, src)| Self::decode_base64(src)),
+        )
     }
 
     /// Execute the given JavaScript `script` in the current browser session.
     ///
     /// `args` is available to the script inside the `arguments` array. Since `Element` implements
-    /// `ToJson`, you can also provide serialized `Element`s as arguments, and they will correctly
-    /// serialize to DOM elements on the other side.
+    /// `Serialize`, you can also provide `Element`s as arguments (through `serde_json::to_value`),
+    /// and they will correctly serialize to DOM elements on the other side.
     pub fn execute(
         &self,
         script: &str,
-        mut args: Vec<Json>,
-    ) -> impl Future<Item = Json, Error = error::CmdError> + 'static {
-        self.fixup_elements(&mut args);
-        let cmd = webdriver::command::JavascriptCommandParameters {
-            script: script.to_string(),
-            args: webdriver::common::Nullable::Value(args),
+        args: Vec<Value>,
+    ) -> impl Future<Item = Value, Error = error::CmdError> + 'static {
+        let cmd = self.script_cmd(script, args, false);
+        self.dup().issue_wd_cmd(cmd).map(|(_, v)| v)
+    }
+
+    /// Execute the given asynchronous JavaScript `script` in the current browser session.
+    ///
+    /// Like with `execute`, `args` is available to the script inside the `arguments` array.
+    /// However, the driver also appends a callback to `arguments`, and the script only completes
+    /// once that callback (`arguments[arguments.length - 1]`) is called. The value passed to the
+    /// callback is the result of the script. For example, to wait for a `fetch` to complete:
+    ///
+    /// ```js
+    /// var done = arguments[arguments.length - 1];
+    /// fetch(arguments[0]).then(function(r) { return r.text(); }).then(done);
+    /// ```
+    ///
+    /// If the callback is not called before the session's script timeout (30 seconds by default)
+    /// expires, the command fails with `ErrorStatus::ScriptTimeout`.
+    pub fn execute_async(
+        &self,
+        script: &str,
+        args: Vec<Value>,
+    ) -> impl Future<Item = Value, Error = error::CmdError> + 'static {
+        let cmd = self.script_cmd(script, args, true);
+        self.dup().issue_wd_cmd(cmd).map(|(_, v)| v)
+    }
+
+    /// Execute the given JavaScript `script` like `execute`, but convert any DOM elements it
+    /// returns into `Element`s.
+    ///
+    /// Elements are converted wherever they appear in the result, including inside arrays and
+    /// objects. For example, `return document.querySelectorAll("a")` can be turned into a
+    /// `Vec<Element>` with `ScriptValue::into_elements`.
+    pub fn execute_with_elements(
+        &self,
+        script: &str,
+        args: Vec<Value>,
+    ) -> impl Future<Item = ScriptValue, Error = error::CmdError> + 'static {
+        let cmd = self.script_cmd(script, args, false);
+        self.dup()
+            .issue_wd_cmd(cmd)
+            .map(|(this, v)| ScriptValue::from_json(&this, v))
+    }
+
+    /// Execute the given JavaScript `script` like `execute`, but with serde-typed arguments and
+    /// result.
+    ///
+    /// `args` must serialize to a JSON array, such as a tuple or a `Vec`, whose items then become
+    /// the script's `arguments`. `Element`s serialize as web element references, so they can be
+    /// passed as (or inside) arguments. The value returned by the script is deserialized into
+    /// `R`; if that fails, `CmdError::TypeMismatch` reports where in the value the mismatch was
+    /// found.
+    pub fn execute_typed<A, R>(
+        &self,
+        script: &str,
+        args: &A,
+    ) -> impl Future<Item = R, Error = error::CmdError> + 'static
+    where
+        A: serde::Serialize,
+        R: serde::de::DeserializeOwned + 'static,
+    {
+        match script::args_to_json(args) {
+            Ok(args) => future::Either::A(self.execute(script, args).and_then(script::from_json)),
+            Err(e) => future::Either::B(future::err(e)),
+        }
+    }
+
+    /// Execute the given asynchronous JavaScript `script` like `execute_async`, but with
+    /// serde-typed arguments and result.
+    ///
+    /// See `execute_typed` for details.
+    pub fn execute_async_typed<A, R>(
+        &self,
+        script: &str,
+        args: &A,
+    ) -> impl Future<Item = R, Error = error::CmdError> + 'static
+    where
+        A: serde::Serialize,
+        R: serde::de::DeserializeOwned + 'static,
+    {
+        match script::args_to_json(args) {
+            Ok(args) => {
+                future::Either::A(self.execute_async(script, args).and_then(script::from_json))
+            }
+            Err(e) => future::Either::B(future::err(e)),
+        }
+    }
+
+    /// Execute the given asynchronous JavaScript `script` like `execute_async`, but convert any
+    /// DOM elements it produces into `Element`s.
+    ///
+    /// See `execute_with_elements` for details.
+    pub fn execute_async_with_elements(
+        &self,
+        script: &str,
+        args: Vec<Value>,
+    ) -> impl Future<Item = ScriptValue, Error = error::CmdError> + 'static {
+        let cmd = self.script_cmd(script, args, true);
+        self.dup()
+            .issue_wd_cmd(cmd)
+            .map(|(this, v)| ScriptValue::from_json(&this, v))
+    }
+
+    /// Register a script to run on every page this client navigates to.
+    ///
+    /// This is useful for shims that must be in place on every page, such as fake timers,
+    /// analytics stubs, or error hooks. The script is also run on the current page right away. It
+    /// runs inside a function, so any globals it wants to define must be assigned to `window`
+    /// explicitly. It runs at most once per document.
+    ///
+    /// When the browser is Chrome, the script is registered through the DevTools protocol, so
+    /// that it runs before any of the page's own scripts, and also on pages the browser navigates
+    /// to by itself (for example through `location.href = ...`). When it is Firefox, the script
+    /// is installed as a temporary add-on to the same effect, except that it is run as an inline
+    /// `<script>`, which pages that forbid those through their Content Security Policy will block.
+    /// Otherwise, the script is injected right after each `goto`, `back`, `forward`, `refresh`,
+    /// `Element::click`, and form submission, at which point the page's own scripts have already
+    /// run.
+    ///
+    /// If the script cannot be run on the current page, it is not registered at all.
+    pub fn preload(
+        &self,
+        script: &str,
+    ) -> impl Future<Item = PreloadScript, Error = error::CmdError> + 'static {
+        let id = self.0.preload.borrow_mut().next_id();
+        let source = preload::wrap(id, script);
+
+        let native = if self.is_chrome() {
+            let params = json!({ "source": source });
+            future::Either::A(future::Either::A(
+                self.cdp("Page.addScriptToEvaluateOnNewDocument", params)
+                    .map(|(this, res)| {
+                        let native = res
+                            .get("identifier")
+                            .and_then(|i| i.as_str())
+                            .map(|i| preload::Native::Cdp(i.to_string()));
+                        (this, native)
+                    })
+                    .or_else({
+                        let this = self.dup();
+                        move |e| match e {
+                            // not all chromedrivers expose the DevTools protocol
+                            error::CmdError::Standard(WebDriverError {
+                                error: ErrorStatus::UnknownCommand,
+                                ..
+                            }) => Ok((this, None)),
+                            e => Err(e),
+                        }
+                    }),
+            ))
+        } else if self.is_firefox() {
+            future::Either::A(future::Either::B(self.install_preload_addon(id, &source)))
+        } else {
+            future::Either::B(future::ok((self.dup(), None)))
+        };
+
+        native.and_then(move |(this, native)| {
+            this.execute(&source, vec![]).then(move |r| match r {
+                Ok(_) => {
+                    this.0
+                        .preload
+                        .borrow_mut()
+                        .insert(preload::Entry { id, source, native });
+                    future::Either::A(future::ok(id))
+                }
+                Err(e) => {
+                    // don't leave behind a script the caller has no handle for
+                    let undone = match native {
+                        Some(native) => future::Either::A(this.remove_native_preload(native)),
+                        None => future::Either::B(future::ok(())),
+                    };
+                    future::Either::B(undone.then(move |_| Err(e)))
+                }
+            })
+        })
+    }
+
+    /// Stop running the given preload script on new pages.
+    ///
+    /// This does not undo any effects the script has already had on the current page.
+    pub fn remove_preload(
+        &self,
+        script: PreloadScript,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'static {
+        let entry = self.0.preload.borrow_mut().remove(script);
+        match entry.and_then(|e| e.native) {
+            Some(native) => future::Either::A(self.remove_native_preload(native)),
+            None => future::Either::B(future::ok(())),
+        }
+    }
+
+    /// Install `source` as a Firefox add-on, so that it runs on every new document.
+    ///
+    /// Drivers that cannot install add-ons from their contents yield `None`.
+    fn install_preload_addon(
+        &self,
+        id: PreloadScript,
+        source: &str,
+    ) -> impl Future<Item = (Self, Option<preload::Native>), Error = error::CmdError> + 'static
+    {
+        let addon = match preload::addon(id, source) {
+            Ok(addon) => addon,
+            Err(e) => return future::Either::B(future::err(error::CmdError::FileSystem(e))),
+        };
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: "moz/addon/install".to_string(),
+            body: Some(json!({ "addon": addon, "temporary": true })),
+        };
+
+        let this = self.dup();
+        future::Either::A(
+            self.dup()
+                .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+                .and_then(|(this, res)| match res {
+                    Value::String(addon) => Ok((this, Some(preload::Native::Addon(addon)))),
+                    v => Err(error::CmdError::NotW3C(v)),
+                })
+                .or_else(move |e| match e {
+                    // older geckodrivers only install add-ons from a path on their own machine
+                    error::CmdError::Standard(WebDriverError {
+                        error: ErrorStatus::UnknownCommand,
+                        ..
+                    })
+                    | error::CmdError::Standard(WebDriverError {
+                        error: ErrorStatus::InvalidArgument,
+                        ..
+                    }) => Ok((this, None)),
+                    e => Err(e),
+                }),
+        )
+    }
+
+    /// Stop the browser from running a preload script by itself.
+    fn remove_native_preload(
+        &self,
+        native: preload::Native,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'static {
+        match native {
+            preload::Native::Cdp(identifier) => {
+                let params = json!({ "identifier": identifier });
+                future::Either::A(
+                    self.cdp("Page.removeScriptToEvaluateOnNewDocument", params)
+                        .map(|_| ()),
+                )
+            }
+            preload::Native::Addon(addon) => {
+                let cmd = RawCmd {
+                    method: Method::Post,
+                    path: "moz/addon/uninstall".to_string(),
+                    body: Some(json!({ "id": addon })),
+                };
+                future::Either::B(
+                    self.dup()
+                        .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+                        .map(|_| ()),
+                )
+            }
+        }
+    }
+
+    /// Start capturing console output, uncaught errors, and unhandled promise rejections on every
+    /// page this client visits.
+    ///
+    /// The hooks that do so are installed with `preload`, and the returned handle can be passed to
+    /// `remove_preload` to stop capturing new pages. Captured entries are buffered in the page
+    /// until they are read with `logs`, so entries that have not been read when the client
+    /// navigates away from a page are lost. Unless the hooks are preloaded by the browser (see
+    /// `preload`), anything logged while a page is loading is also missed.
+    pub fn capture_logs(
+        &self,
+    ) -> impl Future<Item = PreloadScript, Error = error::CmdError> + 'static {
+        self.preload(logs::HOOKS)
+    }
+
+    /// Read, and clear, the log entries captured on the current page since they were last read.
+    ///
+    /// Legacy drivers that support the [log command] report the browser's own log instead, which
+    /// includes entries from before `capture_logs` was called, and from previous pages. Those
+    /// entries all have `LogSource::Browser` as their source.
+    ///
+    /// [log command]: https://github.com/SeleniumHQ/selenium/wiki/JsonWireProtocol#sessionsessionidlog
+    pub fn logs(&self) -> impl Future<Item = Vec<LogEntry>, Error = error::CmdError> + 'static {
+        let buffered = {
+            let this = self.dup();
+            move || {
+                this.execute(logs::DRAIN, vec![])
+                    .and_then(|v| Self::parse_logs(v, LogEntry::from_hook))
+            }
+        };
+
+        if !self.0.legacy {
+            return future::Either::B(buffered());
+        }
+
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: "log".to_string(),
+            body: Some(json!({ "type": "browser" })),
+        };
+        future::Either::A(
+            self.dup()
+                .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+                .and_then(|(_, v)| Self::parse_logs(v, LogEntry::from_legacy))
+                .or_else(move |e| match e {
+                    error::CmdError::Standard(WebDriverError {
+                        error: ErrorStatus::UnknownCommand,
+                        ..
+                    }) => future::Either::A(buffered()),
+                    e => future::Either::B(future::err(e)),
+                }),
+        )
+    }
+
+    /// Get all cookies associated with the current document.
+    pub fn get_all_cookies(
+        &self,
+    ) -> impl Future<Item = Vec<Cookie>, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::GetCookies)
+            .and_then(|(_, cookies)| Self::parse_cookies(cookies))
+    }
+
+    /// Get the cookie with the given name, if it is associated with the current document.
+    ///
+    /// If no such cookie exists, a `CmdError::Standard` with `ErrorStatus::NoSuchCookie` is
+    /// returned.
+    pub fn get_named_cookie(
+        &self,
+        name: &str,
+    ) -> impl Future<Item = Cookie, Error = error::CmdError> + 'static {
+        let name = name.to_string();
+        if self.0.legacy {
+            // the legacy protocol has no endpoint for fetching a single cookie
+            return future::Either::A(self.get_all_cookies().and_then(move |cookies| {
+                cookies.into_iter().find(|c| c.name == name).ok_or_else(|| {
+                    error::CmdError::from(WebDriverError::new(
+                        ErrorStatus::NoSuchCookie,
+                        format!("no cookie named '{}'", name),
+                    ))
+                })
+            }));
+        }
+
+        future::Either::B(
+            self.dup()
+                .issue_wd_cmd(WebDriverCommand::GetNamedCookie(name))
+                .and_then(|(_, cookie)| match Cookie::from_json(&cookie) {
+                    Some(c) => Ok(c),
+                    None => Err(error::CmdError::NotW3C(cookie)),
+                }),
+        )
+    }
+
+    /// Add the given cookie to the current browsing context.
+    ///
+    /// If the cookie has no `domain`, the browser will scope it to the current document.
+    pub fn add_cookie(
+        &self,
+        cookie: Cookie,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        // NOTE: the webdriver crate's AddCookieParameters predates sameSite
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: "cookie".to_string(),
+            body: Some(json!({ "cookie": cookie.to_json() })),
         };
 
         self.dup()
-            .issue_wd_cmd(WebDriverCommand::ExecuteScript(cmd))
-            .map(|(_, v)| v)
+            .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+            .map(|(this, _)| this)
+    }
+
+    /// Delete the cookie with the given name from the current browsing context.
+    pub fn delete_cookie(
+        &self,
+        name: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::DeleteCookie(name.to_string()))
+            .map(|(this, _)| this)
+    }
+
+    /// Delete all cookies associated with the current document.
+    pub fn delete_all_cookies(
+        &self,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::DeleteCookies)
+            .map(|(this, _)| this)
     }
 
     /// Issue an HTTP request to the given `url` with all the same cookies as the current session.
@@ -1073,6 +1806,15 @@ impl Client {
     ///
     /// Before the HTTP request is issued, the given `before` closure will be called with a handle
     /// to the `Request` about to be sent.
+    ///
+    /// The cookies sent are chosen from the browser's cookies by the domain, path, `secure`, and
+    /// expiry rules from [RFC 6265] for the target `url`. The browser is not navigated.
+    ///
+    /// When the browser is Chrome, all of its cookies are considered, through the DevTools
+    /// protocol. Other drivers only expose the cookies visible to the current document, so
+    /// cookies for other hosts, or for paths the current document is not under, are not sent.
+    ///
+    /// [RFC 6265]: https://tools.ietf.org/html/rfc6265#section-5.4
     pub fn with_raw_client_for<F>(
         &self,
         method: Method,
@@ -1083,106 +1825,157 @@ impl Client {
         F: FnOnce(&mut hyper::Request) + 'static,
     {
         let url = url.to_owned();
-        // We need to do some trickiness here. GetCookies will only give us the cookies for the
-        // *current* domain, whereas we want the cookies for `url`'s domain. So, we navigate to the
-        // URL in question, fetch its cookies, and then navigate back. *Except* that we can't do
-        // that either (what if `url` is some huge file?). So we *actually* navigate to some weird
-        // url that's unlikely to exist on the target doamin, and which won't resolve into the
-        // actual content, but will still give the same cookies.
-        //
-        // The fact that cookies can have /path and security constraints makes this even more of a
-        // pain. /path in particular is tricky, because you could have a URL like:
-        //
-        //    example.com/download/some_identifier/ignored_filename_just_for_show
-        //
-        // Imagine if a cookie is set with path=/download/some_identifier. How do we get that
-        // cookie without triggering a request for the (large) file? I don't know. Hence: TODO.
         self.current_url_()
             .and_then(move |(this, old_url)| {
-                old_url
-                    .clone()
-                    .join(&url)
-                    .map(move |url| (this, url))
-                    .map_err(|e| e.into())
-            })
-            .and_then(|(this, url)| {
-                url.clone()
-                    .join("/please_give_me_your_cookies")
-                    .map(move |cookie_url| (this, url, cookie_url))
-                    .map_err(|e| e.into())
+                let url = old_url.join(&url)?;
+                Ok((this, old_url, url))
             })
-            .and_then(|(this, url, cookie_url)| {
-                this.goto(cookie_url.as_str()).map(|this| (this, url))
+            .and_then(|(this, old_url, url)| {
+                this.session_cookies()
+                    .map(move |(this, cookies)| (this, old_url, url, cookies))
             })
-            .and_then(|(this, url)| {
-                this.issue_wd_cmd(WebDriverCommand::GetCookies)
-                    .then(|cookies| {
-                        match cookies {
-                            Ok((this, cookies)) => if cookies.is_array() {
-                                future::ok((this, url, cookies))
-                            } else {
-                                future::err(error::CmdError::NotW3C(cookies))
-                            },
-                            Err(e) => {
-                                // TODO: go back before we return
-                                // can't get a handle to this here though :(
-                                //future::Either::B(
-                                //    this.goto(&format!("{}", old_url))
-                                //        .and_then(move |_| future::err(e)),
-                                //)
-                                future::err(e)
-                            }
-                        }
-                    })
-            })
-            .and_then(|(this, url, cookies)| this.back().map(|this| (this, url, cookies)))
-            .and_then(|(this, url, cookies)| {
-                let cookies = cookies.into_array().unwrap();
-
-                // now add all the cookies
-                let mut all_ok = true;
-                let mut jar = hyper::header::Cookie::new();
-                for cookie in &cookies {
-                    if !cookie.is_object() {
-                        all_ok = false;
-                        break;
-                    }
-
-                    // https://w3c.github.io/webdriver/webdriver-spec.html#cookies
-                    let cookie = cookie.as_object().unwrap();
-                    if !cookie.contains_key("name") || !cookie.contains_key("value") {
-                        all_ok = false;
-                        break;
-                    }
-
-                    if !cookie["name"].is_string() || !cookie["value"].is_string() {
-                        all_ok = false;
-                        break;
-                    }
-
-                    // Note that since we're sending these cookies, all that matters is the mapping
-                    // from name to value. The other fields only matter when deciding whether to
-                    // include a cookie or not, and the driver has already decided that for us
-                    // (GetCookies is for a particular URL).
-                    jar.append(
-                        cookie["name"].as_string().unwrap().to_owned(),
-                        cookie["value"].as_string().unwrap().to_owned(),
-                    );
+            .and_then(move |(this, old_url, url, cookies)| {
+                let jar = cookies::jar_for(&cookies, &url, &old_url);
+
+                let mut req = hyper::client::Request::new(method, url.as_ref().parse().unwrap());
+                req.headers_mut().set(jar);
+                if let Some(ref s) = *this.0.ua.borrow() {
+                    req.headers_mut()
+                        .set(hyper::header::UserAgent::new(s.to_owned()));
                 }
+                before(&mut req);
+                this.0.c.request(req).map_err(|e| e.into())
+            })
+    }
 
-                if all_ok {
-                    let mut req =
-                        hyper::client::Request::new(method, url.as_ref().parse().unwrap());
-                    req.headers_mut().set(jar);
-                    if let Some(ref s) = *this.0.ua.borrow() {
-                        req.headers_mut()
-                            .set(hyper::header::UserAgent::new(s.to_owned()));
-                    }
-                    before(&mut req);
-                    future::Either::A(this.0.c.request(req).map_err(|e| e.into()))
-                } else {
-                    future::Either::B(future::err(error::CmdError::NotW3C(Json::Array(cookies))))
-                }
+    /// Download the file at `url` to `dest` using the cookies of the current session.
+    ///
+    /// If `dest` is an existing directory, the file is saved inside it under the name given by the
+    /// server's `Content-Disposition` header, or the last segment of the URL if there is none.
+    /// Otherwise, the file is written to `dest` directly.
+    ///
+    /// The response body is streamed to disk, and `progress` is called with the number of bytes
+    /// written so far and the total size (if the server announced one) after every chunk. The body
+    /// is first written to a hidden file next to the destination, which is only renamed into
+    /// place once the whole body has been written, and is removed if the download fails.
+    ///
+    /// The file is written with blocking I/O on the thread that drives the returned future, one
+    /// network chunk at a time. Other futures on the same event loop are held up while a chunk is
+    /// being written.
+    ///
+    /// Redirects are followed. The cookies sent with each request are chosen for that request's
+    /// URL as described for `with_raw_client_for`, and any cookies set by redirect responses are
+    /// kept for subsequent requests. Cookies for one host are thus never leaked to another. Note
+    /// that cookies set along the way are not added to the browser. Since the download itself is
+    /// a `GET`, every redirect is followed with a `GET` as well, whatever its status code.
+    pub fn download<P, F>(
+        &self,
+        url: &str,
+        dest: P,
+        mut progress: F,
+    ) -> impl Future<Item = Download, Error = error::CmdError> + 'static
+    where
+        P: Into<std::path::PathBuf>,
+        F: FnMut(u64, Option<u64>) + 'static,
+    {
+        let url = url.to_owned();
+        let dest = dest.into();
+        self.current_url_()
+            .and_then(move |(this, origin)| {
+                let url = origin.join(&url)?;
+                Ok((this, origin, url))
+            })
+            .and_then(|(this, origin, url)| {
+                this.session_cookies()
+                    .map(move |(this, jar)| (this, origin, url, jar))
+            })
+            .and_then(|(this, origin, url, jar)| {
+                future::loop_fn(
+                    (this, jar, url, 0),
+                    move |(this, mut jar, url, redirects)| {
+                        let mut req =
+                            hyper::client::Request::new(Method::Get, url.as_ref().parse().unwrap());
+                        req.headers_mut().set(cookies::jar_for(&jar, &url, &origin));
+                        if let Some(ref s) = *this.0.ua.borrow() {
+                            req.headers_mut()
+                                .set(hyper::header::UserAgent::new(s.to_owned()));
+                        }
+
+                        this.0
+                            .c
+                            .request(req)
+                            .map_err(error::CmdError::from)
+                            .and_then(move |res| match download::next_hop(&res, &url, &mut jar)? {
+                                None => Ok(future::Loop::Break((res, url, redirects))),
+                                Some(next) => {
+                                    if redirects == download::MAX_REDIRECTS {
+                                        return Err(error::CmdError::Download(
+                                            error::DownloadError::TooManyRedirects(next),
+                                        ));
+                                    }
+                                    Ok(future::Loop::Continue((this, jar, next, redirects + 1)))
+                                }
+                            })
+                    },
+                )
+            })
+            .and_then(move |(res, url, redirects)| {
+                let suggested_filename = download::suggested_filename(&res);
+                let content_type = res
+                    .headers()
+                    .get::<hyper::header::ContentType>()
+                    .map(|ct| ct.to_string());
+                let total = res
+                    .headers()
+                    .get::<hyper::header::ContentLength>()
+                    .map(|l| l.0);
+
+                let path =
+                    download::target_path(dest, suggested_filename.as_ref().map(|s| &**s), &url);
+                let partial = download::partial_path(&path);
+                let file = match std::fs::File::create(&partial) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        return future::Either::B(future::err(error::CmdError::FileSystem(e)));
+                    }
+                };
+
+                let summary = Download {
+                    path,
+                    url,
+                    bytes: 0,
+                    content_type,
+                    suggested_filename,
+                    redirects,
+                };
+                future::Either::A(
+                    res.body()
+                        .map_err(error::CmdError::from)
+                        .fold((file, summary), move |(mut file, mut summary), chunk| {
+                            use std::io::Write;
+                            file.write_all(&chunk)
+                                .map_err(error::CmdError::FileSystem)?;
+                            summary.bytes += chunk.len() as u64;
+                            progress(summary.bytes, total);
+                            Ok::<_, error::CmdError>((file, summary))
+                        })
+                        .then(move |r| match r {
+                            Ok((file, summary)) => {
+                                drop(file);
+                                match std::fs::rename(&partial, &summary.path) {
+                                    Ok(()) => Ok(summary),
+                                    Err(e) => {
+                                        let _ = std::fs::remove_file(&partial);
+                                        Err(error::CmdError::FileSystem(e))
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                let _ = std::fs::remove_file(&partial);
+                                Err(e)
+                            }
+                        }),
+                )
             })
     }
 
@@ -1234,11 +2027,119 @@ impl Client {
         })
     }
 
+    /// Get the text of the currently displayed user prompt (`alert`, `confirm`, or `prompt`).
+    ///
+    /// If no prompt is open, a `CmdError::Standard` with `ErrorStatus::NoSuchAlert` is returned.
+    pub fn alert_text(&self) -> impl Future<Item = String, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::GetAlertText)
+            .and_then(|(_, text)| match text {
+                Value::String(text) => Ok(text),
+                v => Err(error::CmdError::NotW3C(v)),
+            })
+    }
+
+    /// Accept the currently displayed user prompt.
+    ///
+    /// This is equivalent to the user clicking "OK".
+    pub fn accept_alert(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::AcceptAlert)
+            .map(|(this, _)| this)
+    }
+
+    /// Dismiss the currently displayed user prompt.
+    ///
+    /// This is equivalent to the user clicking "Cancel", or closing an `alert`.
+    pub fn dismiss_alert(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::DismissAlert)
+            .map(|(this, _)| this)
+    }
+
+    /// Type the given text into the currently displayed `prompt`.
+    ///
+    /// The prompt still needs to be accepted with `accept_alert` for the text to be submitted.
+    pub fn send_alert_text(
+        &self,
+        text: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let cmd = WebDriverCommand::SendAlertText(webdriver::command::SendKeysParameters {
+            text: text.to_string(),
+        });
+        self.dup().issue_wd_cmd(cmd).map(|(this, _)| this)
+    }
+
+    /// Perform the given chain of input actions.
+    ///
+    /// Keys and buttons that are still pressed when the chain ends stay pressed until
+    /// `release_actions` is called. Since the actions *may* result in navigation, preload scripts
+    /// are re-injected afterwards.
+    ///
+    /// Legacy drivers do not support input actions.
+    pub fn perform_actions(
+        &self,
+        actions: Actions,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        // NOTE: the webdriver crate's ActionsParameters predates wheel input sources
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: "actions".to_string(),
+            body: Some(actions.to_json()),
+        };
+
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+            .and_then(|(this, _)| this.run_preloads())
+    }
+
+    /// Release all keys and pointer buttons that are currently pressed.
+    pub fn release_actions(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup()
+            .issue_wd_cmd(WebDriverCommand::ReleaseActions)
+            .map(|(this, _)| this)
+    }
+
+    /// Wait for the current page to have the given title.
+    ///
+    /// This can be useful to wait for a navigation triggered by a click or a script to complete.
+    /// The title is checked every 50ms, and if it still does not match after `timeout`,
+    /// `CmdError::TitleTimeout` is returned.
+    pub fn wait_for_title<'a>(
+        &'a self,
+        title: &'a str,
+        timeout: Duration,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        let deadline = Instant::now() + timeout;
+        future::loop_fn((), move |_| {
+            self.title().and_then(move |current| {
+                if current == title {
+                    return future::Either::A(future::ok(future::Loop::Break(())));
+                }
+                if Instant::now() >= deadline {
+                    return future::Either::A(future::err(error::CmdError::TitleTimeout(current)));
+                }
+
+                let pause = tokio_core::reactor::Timeout::new(
+                    Duration::from_millis(navigation::POLL_INTERVAL),
+                    &self.0.handle,
+                );
+                future::Either::B(
+                    future::result(pause)
+                        .flatten()
+                        .map_err(error::CmdError::Lost)
+                        .map(|_| future::Loop::Continue(())),
+                )
+            })
+        })
+    }
+
     /// Wait for the page to navigate to a new URL before proceeding.
     ///
     /// If the `current` URL is not provided, `self.current_url()` will be used. Note however that
     /// this introduces a race condition: the browser could finish navigating *before* we call
-    /// `current_url()`, which would lead to an eternal wait.
+    /// `current_url()`, which would lead to an eternal wait. `expect_navigation` does not have
+    /// this problem.
     pub fn wait_for_navigation(
         self,
         current: Option<url::Url>,
@@ -1277,7 +2178,11 @@ impl Client {
             .issue_wd_cmd(WebDriverCommand::FindElement(search.into()))
             .and_then(|(this, res)| {
                 let f = this.parse_lookup(res);
-                f.map(move |f| Form { c: this, f: f })
+                f.map(move |f| Form {
+                    c: this,
+                    f,
+                    validate: false,
+                })
             })
     }
 
@@ -1295,11 +2200,252 @@ impl Client {
             })
     }
 
-    /// Extract the `WebElement` from a `FindElement` or `FindElementElement` command.
-    fn parse_lookup(&self, res: Json) -> Result<webdriver::common::WebElement, error::CmdError> {
-        if !res.is_object() {
-            return Err(error::CmdError::NotW3C(res));
+    /// Build a full-page screenshot by scrolling through the page and stitching together
+    /// screenshots of each viewport.
+    fn stitch_screenshot(&self) -> impl Future<Item = Vec<u8>, Error = error::CmdError> + 'static {
+        let this = self.dup();
+        let metrics = "\
+            var d = document.documentElement;\
+            var b = document.body;\
+            return [\
+                Math.max(d.scrollHeight, b ? b.scrollHeight : 0),\
+                d.clientHeight,\
+                window.devicePixelRatio || 1,\
+                window.pageXOffset,\
+                window.pageYOffset\
+            ];";
+        self.execute(metrics, vec![])
+            .and_then(|m| {
+                let nums = m
+                    .as_array()
+                    .and_then(|a| a.iter().map(|n| n.as_f64()).collect::<Option<Vec<_>>>());
+                match nums {
+                    Some(ref n) if n.len() == 5 && n[1] > 0.0 => Ok((n[0], n[1], n[2], n[3], n[4])),
+                    _ => Err(error::CmdError::NotW3C(m)),
+                }
+            })
+            .and_then(move |(height, viewport, dpr, x, y)| {
+                let restorer = this.dup();
+                future::loop_fn(
+                    (this, None::<image::Image>, 0.0),
+                    move |(this, canvas, at)| {
+                        // after the first viewport, hide anything that stays put while scrolling
+                        let scroll = "\
+                            if (arguments[1]) {\
+                                var all = document.querySelectorAll('body *');\
+                                for (var i = 0; i < all.length; i++) {\
+                                    var e = all[i];\
+                                    var p = window.getComputedStyle(e).position;\
+                                    if ((p === 'fixed' || p === 'sticky')\
+                                        && !e.hasAttribute('data-fantoccini-hidden')) {\
+                                        e.setAttribute('data-fantoccini-hidden', e.style.visibility);\
+                                        e.style.visibility = 'hidden';\
+                                    }\
+                                }\
+                            }\
+                            window.scrollTo(window.pageXOffset, arguments[0]);\
+                            return window.pageYOffset;";
+                        let args = vec![json!(at), json!(canvas.is_some())];
+                        this.execute(scroll, args)
+                            .and_then(|actual| match actual.as_f64() {
+                                Some(actual) => Ok(actual),
+                                None => Err(error::CmdError::NotW3C(actual)),
+                            })
+                            .and_then(move |actual| {
+                                this.screenshot()
+                                    .map(move |png| (this, canvas, actual, png))
+                            })
+                            .and_then(move |(this, canvas, actual, png)| {
+                                let frame = image::Image::decode(&png)?;
+                                let mut canvas = canvas.unwrap_or_else(|| {
+                                    image::Image::new(frame.width, (height * dpr).round() as u32)
+                                });
+                                canvas.blit_rows(
+                                    &frame,
+                                    (actual * dpr).round() as u32,
+                                    (viewport * dpr).round() as u32,
+                                );
+
+                                // if the browser did not let us scroll as far as we asked, we have
+                                // reached the bottom of the page
+                                let next = actual + viewport;
+                                if next >= height || actual < at {
+                                    Ok(future::Loop::Break((this, canvas)))
+                                } else {
+                                    Ok(future::Loop::Continue((this, Some(canvas), next)))
+                                }
+                            })
+                    },
+                )
+                .then(move |stitched| {
+                    // put the page back the way it was, even if stitching failed half-way
+                    let restore = "\
+                        var hidden = document.querySelectorAll('[data-fantoccini-hidden]');\
+                        for (var i = 0; i < hidden.length; i++) {\
+                            var e = hidden[i];\
+                            e.style.visibility = e.getAttribute('data-fantoccini-hidden');\
+                            e.removeAttribute('data-fantoccini-hidden');\
+                        }\
+                        window.scrollTo(arguments[0], arguments[1]);";
+                    restorer.execute(restore, vec![json!(x), json!(y)]).then(
+                        move |restored| -> Result<Vec<u8>, error::CmdError> {
+                            let (_, canvas) = stitched?;
+                            restored?;
+                            Ok(canvas.encode()?)
+                        },
+                    )
+                })
+            })
+    }
+
+    /// Returns true if the session is known to be driving Chrome (or Chromium).
+    fn is_chrome(&self) -> bool {
+        !self.0.legacy
+            && self
+                .0
+                .browser
+                .borrow()
+                .as_ref()
+                .map(|b| b == "chrome" || b == "chromium")
+                .unwrap_or(false)
+    }
+
+    fn is_firefox(&self) -> bool {
+        !self.0.legacy
+            && self
+                .0
+                .browser
+                .borrow()
+                .as_ref()
+                .map(|b| b == "firefox")
+                .unwrap_or(false)
+    }
+
+    /// Issue a [DevTools protocol] command through chromedriver.
+    ///
+    /// [DevTools protocol]: https://chromedevtools.github.io/devtools-protocol/
+    fn cdp(
+        &self,
+        cmd: &str,
+        params: Value,
+    ) -> impl Future<Item = (Self, Value), Error = error::CmdError> + 'static {
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: "goog/cdp/execute".to_string(),
+            body: Some(json!({ "cmd": cmd, "params": params })),
+        };
+        self.dup().issue_wd_cmd(WebDriverCommand::Extension(cmd))
+    }
+
+    /// Run any preload scripts that the driver does not inject by itself.
+    ///
+    /// This is called after every command that may have navigated to a new page. Since the
+    /// command itself succeeded, errors that just mean there is no page to inject into right now
+    /// are ignored: the command may have opened a user prompt, or the old page may still be
+    /// unloading.
+    fn run_preloads(self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let script = self.0.preload.borrow().reinjection();
+        let script = match script {
+            Some(script) => script,
+            None => return future::Either::B(future::ok(self)),
+        };
+
+        let this = self.dup();
+        future::Either::A(self.execute(&script, vec![]).then(move |r| match r {
+            Ok(_) => Ok(this),
+            Err(error::CmdError::Standard(WebDriverError {
+                error: ErrorStatus::UnexpectedAlertOpen,
+                ..
+            })) => Ok(this),
+            Err(ref e) if navigation::is_unloading(e) => Ok(this),
+            Err(e) => Err(e),
+        }))
+    }
+
+    /// Get the browser's cookies, for requests issued outside the browser.
+    ///
+    /// On Chrome, this is every cookie the browser has. Elsewhere, it is only the cookies visible
+    /// to the current document, since that is all WebDriver exposes.
+    fn session_cookies(
+        self,
+    ) -> impl Future<Item = (Self, Vec<Cookie>), Error = error::CmdError> + 'static {
+        if !self.is_chrome() {
+            return future::Either::B(self.visible_cookies());
+        }
+
+        let this = self.dup();
+        future::Either::A(
+            self.cdp("Network.getAllCookies", json!({}))
+                .and_then(|(this, res)| {
+                    let parsed = res
+                        .get("cookies")
+                        .and_then(Value::as_array)
+                        .and_then(|cs| cs.iter().map(Cookie::from_cdp).collect::<Option<Vec<_>>>());
+                    match parsed {
+                        Some(cookies) => Ok((this, cookies)),
+                        None => Err(error::CmdError::NotW3C(res)),
+                    }
+                })
+                .or_else(move |e| match e {
+                    // not all chromedrivers expose the DevTools protocol
+                    error::CmdError::Standard(WebDriverError {
+                        error: ErrorStatus::UnknownCommand,
+                        ..
+                    }) => future::Either::A(this.visible_cookies()),
+                    e => future::Either::B(future::err(e)),
+                }),
+        )
+    }
+
+    /// Get the cookies visible to the current document.
+    fn visible_cookies(
+        self,
+    ) -> impl Future<Item = (Self, Vec<Cookie>), Error = error::CmdError> + 'static {
+        self.issue_wd_cmd(WebDriverCommand::GetCookies)
+            .and_then(|(this, cookies)| Ok((this, Self::parse_cookies(cookies)?)))
+    }
+
+    /// Decode a base64-encoded response, such as the one given for a `TakeScreenshot` command.
+    fn decode_base64(src: Value) -> Result<Vec<u8>, error::CmdError> {
+        match src {
+            Value::String(src) => {
+                // some drivers wrap the encoded data over multiple lines
+                let src: String = src.chars().filter(|c| !c.is_whitespace()).collect();
+                base64::decode(&src).map_err(error::CmdError::Base64)
+            }
+            v => Err(error::CmdError::NotW3C(v)),
         }
+    }
+
+    /// Parse the response to a `GetCookies` command.
+    fn parse_cookies(cookies: Value) -> Result<Vec<Cookie>, error::CmdError> {
+        let parsed = match cookies.as_array() {
+            Some(cs) => cs.iter().map(Cookie::from_json).collect::<Option<Vec<_>>>(),
+            None => None,
+        };
+
+        parsed.ok_or_else(|| error::CmdError::NotW3C(cookies))
+    }
+
+    /// Parse a list of log entries with `parse`.
+    fn parse_logs(
+        logs: Value,
+        parse: fn(&Value) -> Option<LogEntry>,
+    ) -> Result<Vec<LogEntry>, error::CmdError> {
+        let parsed = match logs.as_array() {
+            Some(ls) => ls.iter().map(parse).collect::<Option<Vec<_>>>(),
+            None => None,
+        };
+
+        parsed.ok_or_else(|| error::CmdError::NotW3C(logs))
+    }
+
+    /// Extract the `WebElement` from a `FindElement` or `FindElementElement` command.
+    fn parse_lookup(&self, res: Value) -> Result<webdriver::common::WebElement, error::CmdError> {
+        let mut res = match res {
+            Value::Object(res) => res,
+            res => return Err(error::CmdError::NotW3C(res)),
+        };
 
         // legacy protocol uses "ELEMENT" as identifier
         let key = if self.0.legacy {
@@ -1308,13 +2454,12 @@ impl Client {
             ELEMENT_KEY
         };
 
-        let mut res = res.into_object().unwrap();
         if !res.contains_key(key) {
-            return Err(error::CmdError::NotW3C(Json::Object(res)));
+            return Err(error::CmdError::NotW3C(Value::Object(res)));
         }
 
         match res.remove(key) {
-            Some(Json::String(wei)) => {
+            Some(Value::String(wei)) => {
                 return Ok(webdriver::common::WebElement::new(wei));
             }
             Some(v) => {
@@ -1323,15 +2468,32 @@ impl Client {
             None => {}
         }
 
-        Err(error::CmdError::NotW3C(Json::Object(res)))
+        Err(error::CmdError::NotW3C(Value::Object(res)))
     }
 
-    fn fixup_elements(&self, args: &mut [Json]) {
+    /// Build the command that executes `script` with `args`, either synchronously or
+    /// asynchronously.
+    fn script_cmd(&self, script: &str, mut args: Vec<Value>, asynchronous: bool) -> Cmd {
+        self.fixup_elements(&mut args);
+        let path = match (self.0.legacy, asynchronous) {
+            (true, false) => "execute",
+            (true, true) => "execute_async",
+            (false, false) => "execute/sync",
+            (false, true) => "execute/async",
+        };
+        WebDriverCommand::Extension(RawCmd {
+            method: Method::Post,
+            path: path.to_string(),
+            body: Some(json!({ "script": script, "args": args })),
+        })
+    }
+
+    fn fixup_elements(&self, args: &mut [Value]) {
         if self.0.legacy {
             for arg in args {
                 // the serialization of WebElement uses the W3C index,
                 // but legacy implementations need us to use the "ELEMENT" index
-                if let Json::Object(ref mut o) = *arg {
+                if let Value::Object(ref mut o) = *arg {
                     if let Some(wei) = o.remove(ELEMENT_KEY) {
                         o.insert("ELEMENT".to_string(), wei);
                     }
@@ -1353,8 +2515,8 @@ impl Element {
     ) -> impl Future<Item = Option<String>, Error = error::CmdError> + 'static {
         let cmd = WebDriverCommand::GetElementAttribute(self.e.clone(), attribute.to_string());
         self.c.issue_wd_cmd(cmd).and_then(|(_, v)| match v {
-            Json::String(v) => Ok(Some(v)),
-            Json::Null => Ok(None),
+            Value::String(v) => Ok(Some(v)),
+            Value::Null => Ok(None),
             v => Err(error::CmdError::NotW3C(v)),
         })
     }
@@ -1370,8 +2532,8 @@ impl Element {
     ) -> impl Future<Item = Option<String>, Error = error::CmdError> + 'static {
         let cmd = WebDriverCommand::GetElementProperty(self.e.clone(), prop.to_string());
         self.c.issue_wd_cmd(cmd).and_then(|(_, v)| match v {
-            Json::String(v) => Ok(Some(v)),
-            Json::Null => Ok(None),
+            Value::String(v) => Ok(Some(v)),
+            Value::Null => Ok(None),
             v => Err(error::CmdError::NotW3C(v)),
         })
     }
@@ -1380,7 +2542,7 @@ impl Element {
     pub fn text(self) -> impl Future<Item = String, Error = error::CmdError> + 'static {
         let cmd = WebDriverCommand::GetElementText(self.e.clone());
         self.c.issue_wd_cmd(cmd).and_then(|(_, v)| match v {
-            Json::String(v) => Ok(v),
+            Value::String(v) => Ok(v),
             v => Err(error::CmdError::NotW3C(v)),
         })
     }
@@ -1404,10 +2566,23 @@ impl Element {
         self.prop(prop).map(|v| v.unwrap())
     }
 
+    /// Get a PNG-encoded screenshot of just this element.
+    pub fn screenshot(self) -> impl Future<Item = Vec<u8>, Error = error::CmdError> + 'static {
+        let cmd = WebDriverCommand::TakeElementScreenshot(self.e);
+        self.c
+            .issue_wd_cmd(cmd)
+            .and_then(|(_, src)| Client::decode_base64(src))
+    }
+
     /// Simulate the user clicking on this element.
     ///
     /// Note that since this *may* result in navigation, we give up the handle to the element.
     pub fn click(self) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        self.click_().and_then(Client::run_preloads)
+    }
+
+    /// Click on this element, without re-injecting preload scripts afterwards.
+    fn click_(self) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
         let cmd = WebDriverCommand::ElementClick(self.e);
         self.c.issue_wd_cmd(cmd).and_then(move |(c, r)| {
             if r.is_null() || r.as_object().map(|o| o.is_empty()).unwrap_or(false) {
@@ -1419,6 +2594,113 @@ impl Element {
         })
     }
 
+    /// Move the mouse over the center of this element.
+    pub fn hover(self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let actions = Actions::new().move_to(&self, 0, 0);
+        let e = self.e.clone();
+        self.gesture(actions, "hover", 0, 0, None)
+            .map(move |c| Element { c, e })
+    }
+
+    /// Simulate the user double-clicking on this element.
+    ///
+    /// Note that since this *may* result in navigation, we give up the handle to the element.
+    pub fn double_click(self) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let actions = Actions::new().move_to(&self, 0, 0).double_click();
+        self.gesture(actions, "dblclick", 0, 0, None)
+    }
+
+    /// Simulate the user clicking on this element with the right mouse button.
+    ///
+    /// This usually opens a context menu. Note that since this *may* result in navigation, we give
+    /// up the handle to the element.
+    pub fn right_click(self) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let actions = Actions::new().move_to(&self, 0, 0).context_click();
+        self.gesture(actions, "contextmenu", 0, 0, None)
+    }
+
+    /// Simulate the user clicking at the given offset from the center of this element.
+    ///
+    /// Unlike `click`, this does not check that the element is actually what receives the click;
+    /// whatever is at that point on the page does. Note that since this *may* result in
+    /// navigation, we give up the handle to the element.
+    pub fn click_at(
+        self,
+        x: i64,
+        y: i64,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let actions = Actions::new().move_to(&self, x, y).click();
+        self.gesture(actions, "click", x, y, None)
+    }
+
+    /// Simulate the user dragging this element with the mouse, and dropping it onto `target`.
+    ///
+    /// Note that some browsers do not start HTML5 drag-and-drop (as used by `draggable`
+    /// elements) in response to input actions. Since the drop *may* result in navigation, we give
+    /// up the handle to the element.
+    pub fn drag_to(
+        self,
+        target: &Element,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let actions = Actions::new().drag_and_drop(&self, target);
+        self.gesture(actions, "drag", 0, 0, Some(target))
+    }
+
+    /// Scroll the page so that this element is in the center of the viewport, if possible.
+    pub fn scroll_into_view(self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.c
+            .execute(gestures::SCROLL_INTO_VIEW, vec![element_ref(&self.e)])
+            .map(move |_| self)
+    }
+
+    /// Scroll this element into view, and then perform `actions`.
+    ///
+    /// Legacy drivers, and drivers that do not support input actions, get the named `gesture`
+    /// synthesized with DOM events instead. See `gestures::SYNTHESIZE` for the arguments.
+    fn gesture(
+        self,
+        actions: Actions,
+        gesture: &'static str,
+        x: i64,
+        y: i64,
+        target: Option<&Element>,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let args = vec![
+            element_ref(&self.e),
+            Value::from(gesture),
+            Value::from(x),
+            Value::from(y),
+            target.map(|t| element_ref(&t.e)).unwrap_or(Value::Null),
+        ];
+        let synthesize = {
+            let c = self.c.dup();
+            move || {
+                c.execute(gestures::SYNTHESIZE, args)
+                    .and_then(move |_| c.run_preloads())
+            }
+        };
+
+        if self.c.0.legacy {
+            return future::Either::B(synthesize());
+        }
+
+        let c = self.c;
+        let release = c.dup();
+        future::Either::A(
+            c.execute(gestures::SCROLL_INTO_VIEW, vec![element_ref(&self.e)])
+                .and_then(move |_| c.perform_actions(actions))
+                .or_else(move |e| match e {
+                    error::CmdError::Standard(WebDriverError {
+                        error: ErrorStatus::UnknownCommand,
+                        ..
+                    }) => future::Either::A(synthesize()),
+                    // the chain may have failed with a button still pressed, which would
+                    // otherwise stay pressed for later commands
+                    e => future::Either::B(release.release_actions().then(move |_| Err(e))),
+                }),
+        )
+    }
+
     /// Follow the `href` target of the element matching the given CSS selector *without* causing a
     /// click interaction.
     ///
@@ -1428,8 +2710,8 @@ impl Element {
         self.c
             .issue_wd_cmd(cmd)
             .and_then(|(this, href)| match href {
-                Json::String(v) => Ok((this, v)),
-                Json::Null => {
+                Value::String(v) => Ok((this, v)),
+                Value::Null => {
                     let e = WebDriverError::new(
                         webdriver::error::ErrorStatus::InvalidArgument,
                         "cannot follow element without href attribute",
@@ -1446,14 +2728,20 @@ impl Element {
     }
 }
 
-impl rustc_serialize::json::ToJson for Element {
-    fn to_json(&self) -> Json {
-        self.e.to_json()
+impl serde::Serialize for Element {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(ELEMENT_KEY, &self.e.id)?;
+        map.end()
     }
 }
 
 impl Form {
     /// Set the `value` of the given `field` in this form.
+    ///
+    /// This only finds `<input>` elements, and assigns their `value` directly. Use `set` or
+    /// `set_checked` for other kinds of controls.
     pub fn set_by_name<'s>(
         &self,
         field: &str,
@@ -1462,10 +2750,7 @@ impl Form {
         let locator = format!("input[name='{}']", field);
         let locator = Locator::Css(&locator);
         let locator = WebDriverCommand::FindElementElement(self.f.clone(), locator.into());
-        let f = Form {
-            c: self.c.dup(),
-            f: self.f.clone(),
-        };
+        let f = self.dup();
         self.c
             .dup()
             .issue_wd_cmd(locator)
@@ -1474,15 +2759,9 @@ impl Form {
                 f.map(move |f| (this, f))
             })
             .and_then(move |(this, field)| {
-                use rustc_serialize::json::ToJson;
-                let mut args = vec![field.to_json(), Json::String(value.to_string())];
-                this.fixup_elements(&mut args);
-                let cmd = webdriver::command::JavascriptCommandParameters {
-                    script: "arguments[0].value = arguments[1]".to_string(),
-                    args: webdriver::common::Nullable::Value(args),
-                };
-
-                this.issue_wd_cmd(WebDriverCommand::ExecuteScript(cmd))
+                let args = vec![element_ref(&field), Value::String(value.to_string())];
+                let cmd = this.script_cmd("arguments[0].value = arguments[1]", args, false);
+                this.issue_wd_cmd(cmd)
             })
             .and_then(|(_, res)| {
                 if res.is_null() {
@@ -1493,6 +2772,19 @@ impl Form {
             })
     }
 
+    /// Make `submit`, `submit_with` and `submit_using` fail if the browser would block the
+    /// submission because some controls are invalid.
+    ///
+    /// The form is checked just like the browser does when the button is clicked, so the form's
+    /// `novalidate` and the button's `formnovalidate` attributes are respected. If any controls
+    /// are invalid, `CmdError::InvalidForm` is returned with the same report as `validate`, and
+    /// the button is not clicked. Like in the browser, `submit_direct` and `submit_sneaky` are
+    /// not affected.
+    pub fn fail_on_invalid(mut self, fail: bool) -> Self {
+        self.validate = fail;
+        self
+    }
+
     /// Submit this form using the first available submit button.
     ///
     /// `false` is returned if no submit button was not found.
@@ -1507,13 +2799,31 @@ impl Form {
         self,
         button: Locator,
     ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
-        let locator = WebDriverCommand::FindElementElement(self.f, button.into());
+        self.submit_by(button.into()).and_then(Client::run_preloads)
+    }
+
+    /// Submit this form using the button found by `button`, without re-injecting preload scripts
+    /// afterwards.
+    fn submit_by(
+        self,
+        button: webdriver::command::LocatorParameters,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let validate = self.validate;
+        let f = self.f.clone();
+        let locator = WebDriverCommand::FindElementElement(self.f, button);
         self.c
             .issue_wd_cmd(locator)
             .and_then(|(this, res)| {
                 let s = this.parse_lookup(res);
                 s.map(move |s| (this, s))
             })
+            .and_then(move |(this, submit)| {
+                if validate {
+                    future::Either::A(form::check_submission(this, &f, submit))
+                } else {
+                    future::Either::B(future::ok((this, submit)))
+                }
+            })
             .and_then(move |(this, submit)| {
                 this.issue_wd_cmd(WebDriverCommand::ElementClick(submit))
             })
@@ -1552,22 +2862,20 @@ impl Form {
     /// Note that since no button is actually clicked, the `name=value` pair for the submit button
     /// will not be submitted. This can be circumvented by using `submit_sneaky` instead.
     pub fn submit_direct(self) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
-        use rustc_serialize::json::ToJson;
-
-        let mut args = vec![self.f.clone().to_json()];
-        self.c.fixup_elements(&mut args);
+        let args = vec![element_ref(&self.f)];
         // some sites are silly, and name their submit button "submit". this ends up overwriting
         // the "submit" function of the form with a reference to the submit button itself, so we
         // can't call .submit(). we get around this by creating a *new* form, and using *its*
         // submit() handler but with this pointed to the real form. solution from here:
         // https://stackoverflow.com/q/833032/472927#comment23038712_834197
-        let cmd = webdriver::command::JavascriptCommandParameters {
-            script: "document.createElement('form').submit.call(arguments[0])".to_string(),
-            args: webdriver::common::Nullable::Value(args),
-        };
+        let cmd = self.c.script_cmd(
+            "document.createElement('form').submit.call(arguments[0])",
+            args,
+            false,
+        );
 
         self.c
-            .issue_wd_cmd(WebDriverCommand::ExecuteScript(cmd))
+            .issue_wd_cmd(cmd)
             .and_then(move |(this, res)| {
                 if res.is_null() || res.as_object().map(|o| o.is_empty()).unwrap_or(false) {
                     // geckodriver returns {} :(
@@ -1576,6 +2884,7 @@ impl Form {
                     Err(error::CmdError::NotW3C(res))
                 }
             })
+            .and_then(Client::run_preloads)
     }
 
     /// Submit this form directly, without clicking any buttons, and with an extra field.
@@ -1589,35 +2898,45 @@ impl Form {
         field: &str,
         value: &str,
     ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
-        use rustc_serialize::json::ToJson;
-        let mut args = vec![
-            self.f.clone().to_json(),
-            Json::String(field.to_string()),
-            Json::String(value.to_string()),
+        let args = vec![
+            element_ref(&self.f),
+            Value::String(field.to_string()),
+            Value::String(value.to_string()),
         ];
-        self.c.fixup_elements(&mut args);
-        let cmd = webdriver::command::JavascriptCommandParameters {
-            script: "\
-                     var h = document.createElement('input');\
-                     h.setAttribute('type', 'hidden');\
-                     h.setAttribute('name', arguments[1]);\
-                     h.value = arguments[2];\
-                     arguments[0].appendChild(h)"
-                .to_string(),
-            args: webdriver::common::Nullable::Value(args),
-        };
+        let cmd = self.c.script_cmd(
+            "\
+             var h = document.createElement('input');\
+             h.setAttribute('type', 'hidden');\
+             h.setAttribute('name', arguments[1]);\
+             h.value = arguments[2];\
+             arguments[0].appendChild(h)",
+            args,
+            false,
+        );
 
         let f = self.f;
-        self.c
-            .issue_wd_cmd(WebDriverCommand::ExecuteScript(cmd))
-            .and_then(move |(this, res)| {
-                if res.is_null() | res.as_object().map(|o| o.is_empty()).unwrap_or(false) {
-                    // geckodriver returns {} :(
-                    future::Either::A(Form { f: f, c: this }.submit_direct())
-                } else {
-                    future::Either::B(future::err(error::CmdError::NotW3C(res)))
-                }
-            })
+        let validate = self.validate;
+        self.c.issue_wd_cmd(cmd).and_then(move |(this, res)| {
+            if res.is_null() | res.as_object().map(|o| o.is_empty()).unwrap_or(false) {
+                // geckodriver returns {} :(
+                let form = Form {
+                    f,
+                    c: this,
+                    validate,
+                };
+                future::Either::A(form.submit_direct())
+            } else {
+                future::Either::B(future::err(error::CmdError::NotW3C(res)))
+            }
+        })
+    }
+
+    fn dup(&self) -> Self {
+        Form {
+            c: self.c.dup(),
+            f: self.f.clone(),
+            validate: self.validate,
+        }
     }
 }
 
@@ -1734,6 +3053,36 @@ mod tests {
         tester!(raw_inner)
     }
 
+    fn download_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.find(Locator::Css("img.central-featured-logo")))
+            .and_then(|img| {
+                img.attr("src")
+                    .map(|src| src.expect("image should have a src"))
+            })
+            .and_then(move |src| {
+                let mut last = 0;
+                c.download(&src, std::env::temp_dir(), move |bytes, _| {
+                    assert!(bytes > last);
+                    last = bytes;
+                })
+            })
+            .and_then(|download| {
+                assert!(download.bytes > 0);
+                assert_eq!(
+                    std::fs::metadata(&download.path).unwrap().len(),
+                    download.bytes
+                );
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_downloads() {
+        tester!(download_inner)
+    }
+
     fn window_size_inner<'a>(
         c: &'a Client,
     ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
@@ -1771,4 +3120,682 @@ mod tests {
     fn it_can_get_and_set_window_position() {
         tester!(window_position_inner)
     }
+
+    fn history_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://en.wikipedia.org/wiki/Foobar")
+            .and_then(move |_| c.title())
+            .and_then(move |title| {
+                assert_eq!(title, "Foobar - Wikipedia");
+                c.goto("https://en.wikipedia.org/wiki/Foo_Lake")
+            })
+            .and_then(move |_| c.back())
+            .and_then(move |_| c.wait_for_title("Foobar - Wikipedia", Duration::from_secs(10)))
+            .and_then(move |_| c.forward())
+            .and_then(move |_| c.current_url())
+            .and_then(|url| {
+                assert_eq!(url.as_ref(), "https://en.wikipedia.org/wiki/Foo_Lake");
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_navigates_history() {
+        tester!(history_inner)
+    }
+
+    fn cookies_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.delete_all_cookies())
+            .and_then(move |_| {
+                let mut cookie = Cookie::new("fantoccini", "test");
+                cookie.same_site = Some(SameSite::Lax);
+                c.add_cookie(cookie)
+            })
+            .and_then(move |_| c.get_named_cookie("fantoccini"))
+            .and_then(move |cookie| {
+                assert_eq!(cookie.value, "test");
+                c.delete_cookie("fantoccini")
+            })
+            .and_then(move |_| c.get_all_cookies())
+            .and_then(|cookies| {
+                assert!(cookies.iter().all(|c| c.name != "fantoccini"));
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_manages_cookies() {
+        tester!(cookies_inner)
+    }
+
+    fn screenshot_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.screenshot())
+            .and_then(move |png| {
+                assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+                c.find(Locator::Css("img.central-featured-logo"))
+            })
+            .and_then(|img| img.screenshot())
+            .and_then(|png| {
+                assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_takes_screenshots() {
+        tester!(screenshot_inner)
+    }
+
+    fn full_page_screenshot_inner<'a>(
+        c: &'a Client,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://en.wikipedia.org/wiki/Foobar")
+            .and_then(move |_| c.set_window_size(800, 600))
+            .and_then(move |_| c.screenshot())
+            .and_then(move |viewport| {
+                c.full_page_screenshot()
+                    .map(move |full| (image::Image::decode(&viewport), image::Image::decode(&full)))
+            })
+            .and_then(|(viewport, full)| {
+                let (viewport, full) = (viewport.unwrap(), full.unwrap());
+                assert!(full.height > viewport.height);
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_takes_full_page_screenshots() {
+        tester!(full_page_screenshot_inner)
+    }
+
+    fn print_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://en.wikipedia.org/wiki/Foobar")
+            .and_then(move |_| {
+                let mut options = PrintOptions::default();
+                options.orientation = PrintOrientation::Landscape;
+                options.page = PrintPageSize::A4;
+                options.page_ranges.push("1".to_string());
+                c.print(options)
+            })
+            .and_then(|pdf| {
+                assert!(pdf.starts_with(b"%PDF"));
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_prints() {
+        tester!(print_inner)
+    }
+
+    fn execute_async_inner<'a>(
+        c: &'a Client,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| {
+                c.execute_async(
+                    "var n = arguments[0], done = arguments[arguments.length - 1];\
+                     window.setTimeout(function() { done(n + 1); }, 100);",
+                    vec![json!(41)],
+                )
+            })
+            .and_then(|v| {
+                assert_eq!(v.as_u64(), Some(42));
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_executes_async() {
+        tester!(execute_async_inner)
+    }
+
+    fn alerts_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        // the prompt opens right after the script returns, so that the script doesn't block on it
+        let open = |dialog: &str| {
+            format!(
+                "var done = arguments[arguments.length - 1];\
+                 window.setTimeout(function() {{ done(); window.answer = {}; }}, 0);",
+                dialog
+            )
+        };
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.execute_async(&open("prompt('name?')"), vec![]))
+            .and_then(move |_| c.alert_text())
+            .and_then(move |text| {
+                assert_eq!(text, "name?");
+                c.send_alert_text("fantoccini")
+            })
+            .and_then(move |_| c.accept_alert())
+            .and_then(move |_| c.execute("return window.answer;", vec![]))
+            .and_then(move |answer| {
+                assert_eq!(answer, Value::from("fantoccini"));
+                c.execute_async(&open("confirm('sure?')"), vec![])
+            })
+            .and_then(move |_| c.dismiss_alert())
+            .and_then(move |_| c.execute("return window.answer;", vec![]))
+            .and_then(move |answer| {
+                assert_eq!(answer, Value::Bool(false));
+                c.alert_text().then(Ok)
+            })
+            .map(|r| match r {
+                Err(error::CmdError::Standard(WebDriverError {
+                    error: ErrorStatus::NoSuchAlert,
+                    ..
+                })) => {}
+                r => panic!("unexpected result: {:?}", r),
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_handles_alerts() {
+        tester!(alerts_inner)
+    }
+
+    fn script_elements_inner<'a>(
+        c: &'a Client,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| {
+                c.execute_with_elements(
+                    "return { search: document.getElementById('searchInput'), \
+                     links: document.querySelectorAll('#js-link-box-en, #js-link-box-de') };",
+                    vec![],
+                )
+            })
+            .and_then(|v| {
+                let mut o = match v {
+                    ScriptValue::Object(o) => o,
+                    _ => panic!("script did not return an object"),
+                };
+                let links = o.remove("links").unwrap().into_elements().unwrap();
+                assert_eq!(links.len(), 2);
+                let search = o.remove("search").unwrap().into_element().unwrap();
+                search.attr("name")
+            })
+            .and_then(|name| {
+                assert_eq!(name.as_ref().map(String::as_str), Some("search"));
+                Ok(())
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_converts_script_elements() {
+        tester!(script_elements_inner)
+    }
+
+    fn execute_typed_inner<'a>(
+        c: &'a Client,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.find(Locator::Id("searchInput")))
+            .and_then(move |e| {
+                c.execute_typed("return [arguments[0].name, arguments[1] * 2];", &(e, 21))
+            })
+            .and_then(move |(name, n): (String, u32)| {
+                assert_eq!(name, "search");
+                assert_eq!(n, 42);
+                c.execute_typed::<_, Vec<u32>>("return [1, 'two'];", &())
+            })
+            .then(|r| match r {
+                Err(error::CmdError::TypeMismatch(ref path, _)) => {
+                    assert_eq!(path, "[1]");
+                    Ok(())
+                }
+                r => panic!("unexpected result: {:?}", r),
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_executes_typed() {
+        tester!(execute_typed_inner)
+    }
+
+    fn preload_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        let count = "return window.preloaded || 0;";
+        c.preload("window.preloaded = (window.preloaded || 0) + 1;")
+            .and_then(move |id| {
+                c.goto("https://www.wikipedia.org/")
+                    .and_then(move |_| c.execute(count, vec![]))
+                    .map(move |n| (id, n))
+            })
+            .and_then(move |(id, n)| {
+                assert_eq!(n.as_u64(), Some(1));
+                c.refresh()
+                    .and_then(move |_| c.execute(count, vec![]))
+                    .map(move |n| (id, n))
+            })
+            .and_then(move |(id, n)| {
+                assert_eq!(n.as_u64(), Some(1));
+                c.remove_preload(id)
+            })
+            .and_then(move |_| c.refresh())
+            .and_then(move |_| c.execute(count, vec![]))
+            .map(|n| {
+                assert_eq!(n.as_u64(), Some(0));
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_preloads() {
+        tester!(preload_inner)
+    }
+
+    fn logs_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.capture_logs())
+            .and_then(move |_| {
+                c.execute(
+                    "console.warn('careful', { a: 1 });\
+                     window.setTimeout(function() { null.boom; }, 0);",
+                    vec![],
+                )
+            })
+            .and_then(move |_| c.execute_async("window.setTimeout(arguments[0], 100);", vec![]))
+            .and_then(move |_| c.logs())
+            .and_then(move |logs| {
+                if !c.0.legacy {
+                    let warn = logs.iter().find(|l| l.level == LogLevel::Warning).unwrap();
+                    assert_eq!(warn.source, LogSource::Console);
+                    assert_eq!(warn.message, "careful {\"a\":1}");
+                    assert!(logs.iter().any(|l| l.source == LogSource::Exception));
+                }
+                assert!(logs.iter().any(|l| l.level == LogLevel::Error));
+                c.logs()
+            })
+            .and_then(move |logs| {
+                if !c.0.legacy {
+                    assert!(logs.is_empty());
+                }
+                c.execute("console.info('again');", vec![])
+            })
+            .and_then(move |_| c.logs())
+            .map(move |logs| {
+                if !c.0.legacy {
+                    assert_eq!(logs.len(), 1);
+                    assert_eq!(logs[0].level, LogLevel::Info);
+                    assert_eq!(logs[0].message, "again");
+                }
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_captures_logs() {
+        tester!(logs_inner)
+    }
+
+    fn actions_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.find(Locator::Id("searchInput")))
+            .and_then(move |e| {
+                let actions = Actions::new()
+                    .move_to(&e, 0, 0)
+                    .click()
+                    .key_down(Key::SHIFT)
+                    .send_keys("foo")
+                    .key_up(Key::SHIFT)
+                    .send_keys("bar");
+                c.perform_actions(actions)
+            })
+            .and_then(move |_| c.release_actions())
+            .and_then(move |_| c.execute("return document.activeElement.value;", vec![]))
+            .map(|v| {
+                assert_eq!(v.as_str(), Some("FOObar"));
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_performs_actions() {
+        tester!(actions_inner)
+    }
+
+    fn gestures_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        let search = Locator::Id("searchInput");
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| {
+                c.execute(
+                    "window.seen = [];\
+                     var e = document.getElementById('searchInput');\
+                     ['mouseover', 'dblclick', 'contextmenu'].forEach(function(t) {\
+                         e.addEventListener(t, function(ev) {\
+                             window.seen.push(t);\
+                             ev.preventDefault();\
+                         });\
+                     });",
+                    vec![],
+                )
+            })
+            .and_then(move |_| c.find(search))
+            .and_then(|e| e.scroll_into_view())
+            .and_then(|e| e.hover())
+            .and_then(|e| e.double_click())
+            .and_then(move |_| c.find(search))
+            .and_then(|e| e.right_click())
+            .and_then(move |_| c.execute("return window.seen;", vec![]))
+            .map(|seen| {
+                let seen = seen.as_array().unwrap();
+                for t in &["mouseover", "dblclick", "contextmenu"] {
+                    assert!(seen.iter().any(|s| s == t), "no {} event", t);
+                }
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_performs_gestures() {
+        tester!(gestures_inner)
+    }
+
+    fn select_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| {
+                c.execute(
+                    "window.changes = 0;\
+                     document.getElementById('searchLanguage')\
+                         .addEventListener('change', function() { window.changes++; });",
+                    vec![],
+                )
+            })
+            .and_then(move |_| c.find(Locator::Id("searchLanguage")))
+            .map(|e| e.into_select())
+            .and_then(|s| s.select_by_value("de"))
+            .and_then(|s| s.selected_options().map(move |selected| (s, selected)))
+            .and_then(|(s, selected)| {
+                assert_eq!(selected.len(), 1);
+                assert_eq!(selected[0].value, "de");
+                s.select_by_index(0)
+            })
+            .and_then(|s| s.select_by_value("no-such-language").then(|r| Ok((s, r))))
+            .and_then(move |(s, r)| {
+                match r {
+                    Err(error::CmdError::NoSuchElement(..)) => {}
+                    r => panic!("unexpected result: {:?}", r.map(|_| ())),
+                }
+                s.options()
+            })
+            .and_then(move |options| {
+                assert!(options.len() > 1);
+                assert!(options[0].selected);
+                c.execute("return window.changes;", vec![])
+            })
+            .map(|changes| {
+                assert_eq!(changes.as_u64(), Some(2));
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_selects_options() {
+        tester!(select_inner)
+    }
+
+    fn form_controls_inner<'a>(
+        c: &'a Client,
+    ) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.form(Locator::Css("#search-form")))
+            .and_then(|f| f.set("language", "de"))
+            .and_then(|f| f.set("search", "foobar"))
+            .and_then(move |f| {
+                c.execute(
+                    "return [document.getElementById('searchLanguage').value, \
+                             document.getElementById('searchInput').value];",
+                    vec![],
+                )
+                .map(move |v| (f, v))
+            })
+            .and_then(|(f, v)| {
+                assert_eq!(v, json!(["de", "foobar"]));
+                f.set("no-such-field", "x").then(|r| Ok((f, r)))
+            })
+            .and_then(|(f, r)| {
+                match r {
+                    Err(error::CmdError::NoSuchField(ref name)) => {
+                        assert_eq!(name, "no-such-field")
+                    }
+                    r => panic!("unexpected result: {:?}", r.map(|_| ())),
+                }
+                f.set("language", "no-such-language").then(|r| Ok((f, r)))
+            })
+            .and_then(|(f, r)| {
+                match r {
+                    Err(error::CmdError::InvalidArgument(ref name, _)) => {
+                        assert_eq!(name, "language")
+                    }
+                    r => panic!("unexpected result: {:?}", r.map(|_| ())),
+                }
+                f.set_checked("search", true).then(Ok)
+            })
+            .map(|r| match r {
+                Err(error::CmdError::InvalidArgument(..)) => {}
+                r => panic!("unexpected result: {:?}", r.map(|_| ())),
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_sets_form_controls() {
+        tester!(form_controls_inner)
+    }
+
+    fn form_data_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.form(Locator::Css("#search-form")))
+            .and_then(|f| f.set("language", "de"))
+            .and_then(|f| f.set("search", "foobar"))
+            .and_then(|f| f.data().map(move |data| (f, data)))
+            .and_then(|(f, data)| {
+                let get = |name: &str| {
+                    data.iter()
+                        .filter(|&&(ref n, _)| n == name)
+                        .map(|&(_, ref v)| v.clone())
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(get("search"), vec!["foobar".to_string()]);
+                assert_eq!(get("language"), vec!["de".to_string()]);
+                f.controls()
+            })
+            .map(|controls| {
+                let search = controls.iter().find(|c| c.name == "search").unwrap();
+                assert_eq!(search.kind, "search");
+                let language = controls.iter().find(|c| c.name == "language").unwrap();
+                assert_eq!(language.kind, "select-one");
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_reads_form_data() {
+        tester!(form_data_inner)
+    }
+
+    fn form_fill_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        use std::collections::BTreeMap;
+
+        let mut fields = BTreeMap::new();
+        fields.insert("language", "de");
+        fields.insert("search", "foobar");
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.form(Locator::Css("#search-form")))
+            .and_then(move |f| f.fill(fields))
+            .and_then(|f| f.data().map(move |data| (f, data)))
+            .and_then(|(f, data)| {
+                assert!(data.contains(&("language".to_string(), "de".to_string())));
+                assert!(data.contains(&("search".to_string(), "foobar".to_string())));
+                let fields = vec![
+                    ("search", Value::from("typed")),
+                    ("no-such-field", Value::from("x")),
+                    ("language", Value::from("no-such-language")),
+                ];
+                f.fill_typing(fields).then(|r| Ok((f, r)))
+            })
+            .and_then(move |(f, r)| {
+                match r {
+                    Err(error::CmdError::FormFill(ref errors)) => {
+                        let names: Vec<_> = errors.iter().map(|&(ref n, _)| &**n).collect();
+                        assert_eq!(names, vec!["no-such-field", "language"]);
+                    }
+                    r => panic!("unexpected result: {:?}", r.map(|_| ())),
+                }
+                f.data()
+            })
+            .map(|data| {
+                assert!(data.contains(&("search".to_string(), "typed".to_string())));
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_fills_forms() {
+        tester!(form_fill_inner)
+    }
+
+    fn upload_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        use upload::TempFile;
+
+        let a = TempFile::new("a.txt", b"a");
+        let b = TempFile::new("b.txt", b"b");
+        let paths = vec![a.0.clone(), b.0.clone()];
+        let page = "data:text/html,<form><input type=file name=f multiple></form>";
+        let names = "return Array.prototype.map.call(arguments[0].files, \
+                     function(f) { return f.name; });";
+        c.goto(page)
+            .and_then(move |_| c.find(Locator::Css("input[type=file]")))
+            .and_then(move |e| e.upload_files(paths))
+            .and_then(move |e| {
+                // the files only need to exist until they have been chosen
+                drop((a, b));
+                c.execute_typed(names, &(e,))
+            })
+            .map(|names: Vec<String>| {
+                assert_eq!(names.len(), 2);
+                assert!(names[0].ends_with("a.txt"));
+                assert!(names[1].ends_with("b.txt"));
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_uploads_files() {
+        tester!(upload_inner)
+    }
+
+    fn validation_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        let page = "data:text/html,<form id=f>\
+                    <input name=q required><input name=n type=number value=5>\
+                    <button type=submit>Go</button></form>";
+        c.goto(page)
+            .and_then(move |_| c.form(Locator::Id("f")))
+            .and_then(|f| f.validate().map(move |invalid| (f, invalid)))
+            .and_then(|(f, invalid)| {
+                assert_eq!(invalid.len(), 1);
+                assert_eq!(invalid[0].name, "q");
+                assert!(!invalid[0].message.is_empty());
+                f.fail_on_invalid(true).submit().then(Ok)
+            })
+            .and_then(move |r| {
+                match r {
+                    Err(error::CmdError::InvalidForm(ref invalid)) => {
+                        assert_eq!(invalid[0].name, "q");
+                    }
+                    r => panic!("unexpected result: {:?}", r.map(|_| ())),
+                }
+                c.form(Locator::Id("f"))
+            })
+            .and_then(|f| f.set("q", "rust"))
+            .and_then(|f| f.validate())
+            .map(|invalid| {
+                assert!(invalid.is_empty());
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_reports_invalid_forms() {
+        tester!(validation_inner)
+    }
+
+    fn navigation_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        use std::time::Duration;
+        c.goto("https://www.wikipedia.org/")
+            .and_then(move |_| c.form(Locator::Css("#search-form")))
+            .and_then(|f| f.set_by_name("search", "foobar"))
+            .and_then(|f| f.submit_and_wait(Duration::from_secs(10)))
+            .and_then(move |_| c.current_url())
+            .and_then(move |url| {
+                assert_eq!(url.as_ref(), "https://en.wikipedia.org/wiki/Foobar");
+                c.expect_navigation(Duration::from_secs(10), |c| {
+                    c.execute("location.reload();", vec![]).map(move |_| c)
+                })
+            })
+            .and_then(move |_| c.find(Locator::Css("h1")))
+            .and_then(|e| e.click_and_wait(Duration::from_millis(500)).then(Ok))
+            .map(|r| match r {
+                Err(error::CmdError::NavigationTimeout(url)) => {
+                    assert_eq!(url.as_ref(), "https://en.wikipedia.org/wiki/Foobar");
+                }
+                r => panic!("unexpected result: {:?}", r.map(|_| ())),
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_waits_for_navigation() {
+        tester!(navigation_inner)
+    }
+
+    fn load_state_inner<'a>(c: &'a Client) -> impl Future<Item = (), Error = error::CmdError> + 'a {
+        use std::time::Duration;
+        let timeout = Duration::from_secs(10);
+        c.track_network()
+            .and_then(move |_| {
+                c.navigate_with(
+                    "https://www.wikipedia.org/",
+                    PageLoadStrategy::Eager,
+                    timeout,
+                )
+            })
+            .and_then(move |_| c.wait_for_load_state(LoadState::Load, timeout))
+            .and_then(move |_| c.wait_for_load_state(LoadState::NetworkIdle, timeout))
+            .and_then(move |_| c.execute("return document.readyState;", vec![]))
+            .and_then(move |state| {
+                assert_eq!(state, Value::from("complete"));
+                let fetch = "fetch('/').then(function() { window.fetched = true; }); \
+                             window.setTimeout(function() { fetch('/'); }, 200);";
+                c.execute(fetch, vec![])
+            })
+            .and_then(move |_| c.wait_for_load_state(LoadState::NetworkIdle, timeout))
+            .and_then(move |_| c.execute("return window.fetched === true;", vec![]))
+            .and_then(move |fetched| {
+                assert_eq!(fetched, Value::Bool(true));
+                c.navigate_with(
+                    "https://en.wikipedia.org/",
+                    PageLoadStrategy::Normal,
+                    timeout,
+                )
+            })
+            .and_then(move |_| c.current_url())
+            .map(|url| {
+                assert_eq!(url.host_str(), Some("en.wikipedia.org"));
+            })
+    }
+
+    #[test]
+    #[ignore]
+    fn it_waits_for_load_states() {
+        tester!(load_state_inner)
+    }
 }
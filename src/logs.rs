@@ -0,0 +1,197 @@
+use serde_json::Value;
+
+/// The script installed by `Client::capture_logs`.
+///
+/// It buffers console calls, uncaught errors, and unhandled promise rejections in
+/// `window.__fantoccini_logs`. The buffer is capped so that pages that log a lot do not grow
+/// without bound if the logs are never read.
+pub(crate) const HOOKS: &str = "\
+    var buf = window.__fantoccini_logs = window.__fantoccini_logs || [];\
+    var push = function(level, source, args) {\
+        var parts = [];\
+        for (var i = 0; i < args.length; i++) {\
+            var a = args[i];\
+            try {\
+                if (a instanceof Error) {\
+                    parts.push(a.stack || String(a));\
+                } else if (typeof a === 'object' && a !== null) {\
+                    parts.push(JSON.stringify(a));\
+                } else {\
+                    parts.push(String(a));\
+                }\
+            } catch (e) {\
+                parts.push(String(a));\
+            }\
+        }\
+        if (buf.length >= 1000) { buf.shift(); }\
+        buf.push({ level: level, source: source, message: parts.join(' '), timestamp: Date.now() });\
+    };\
+    ['debug', 'log', 'info', 'warn', 'error'].forEach(function(level) {\
+        var original = console[level];\
+        console[level] = function() {\
+            push(level, 'console', arguments);\
+            if (original) { return original.apply(console, arguments); }\
+        };\
+    });\
+    window.addEventListener('error', function(e) {\
+        push('error', 'exception', [e.error || e.message]);\
+    });\
+    window.addEventListener('unhandledrejection', function(e) {\
+        push('error', 'rejection', [e.reason]);\
+    });";
+
+/// The script that empties the buffer filled by `HOOKS`, and returns its contents.
+///
+/// The buffer is emptied in place, since the hooks keep pushing to the same array.
+pub(crate) const DRAIN: &str = "\
+    var buf = window.__fantoccini_logs;\
+    return buf ? buf.splice(0, buf.length) : [];";
+
+/// How severe a log entry is.
+///
+/// Levels are ordered by severity, so `entry.level >= LogLevel::Warning` selects warnings and
+/// errors.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum LogLevel {
+    /// Debugging output, such as from `console.debug`.
+    Debug,
+    /// Informational output, such as from `console.log` or `console.info`.
+    Info,
+    /// A warning, such as from `console.warn`.
+    Warning,
+    /// An error, such as from `console.error` or an uncaught exception.
+    Error,
+}
+
+/// Where a log entry came from.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum LogSource {
+    /// A call to one of the `console` methods.
+    Console,
+    /// An exception that was thrown but never caught.
+    Exception,
+    /// A promise that was rejected without a rejection handler.
+    Rejection,
+    /// The browser's own log, as reported by a legacy driver.
+    ///
+    /// These entries include console output and errors, but do not say which is which.
+    Browser,
+}
+
+/// A single entry read with `Client::logs`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LogEntry {
+    /// How severe the entry is.
+    pub level: LogLevel,
+    /// Where the entry came from.
+    pub source: LogSource,
+    /// The logged message.
+    ///
+    /// For console calls, the arguments are joined with spaces, with objects serialized as JSON.
+    /// For errors, this includes the stack trace if the browser provides one.
+    pub message: String,
+    /// When the entry was logged, in milliseconds since the Unix Epoch.
+    pub timestamp: u64,
+}
+
+impl LogEntry {
+    /// Parse an entry buffered by `HOOKS`.
+    pub(crate) fn from_hook(entry: &Value) -> Option<Self> {
+        let level = match entry.get("level")?.as_str()? {
+            "debug" => LogLevel::Debug,
+            "log" | "info" => LogLevel::Info,
+            "warn" => LogLevel::Warning,
+            "error" => LogLevel::Error,
+            _ => return None,
+        };
+        let source = match entry.get("source")?.as_str()? {
+            "console" => LogSource::Console,
+            "exception" => LogSource::Exception,
+            "rejection" => LogSource::Rejection,
+            _ => return None,
+        };
+
+        Some(LogEntry {
+            level,
+            source,
+            message: entry.get("message")?.as_str()?.to_string(),
+            timestamp: timestamp(entry.get("timestamp")?)?,
+        })
+    }
+
+    /// Parse an entry returned by the legacy [log command].
+    ///
+    /// [log command]: https://github.com/SeleniumHQ/selenium/wiki/JsonWireProtocol#sessionsessionidlog
+    pub(crate) fn from_legacy(entry: &Value) -> Option<Self> {
+        // these are the names of java.util.logging levels
+        let level = match entry.get("level")?.as_str()? {
+            "SEVERE" => LogLevel::Error,
+            "WARNING" => LogLevel::Warning,
+            "INFO" => LogLevel::Info,
+            "CONFIG" | "FINE" | "FINER" | "FINEST" | "DEBUG" | "ALL" => LogLevel::Debug,
+            _ => return None,
+        };
+
+        Some(LogEntry {
+            level,
+            source: LogSource::Browser,
+            message: entry.get("message")?.as_str()?.to_string(),
+            timestamp: timestamp(entry.get("timestamp")?)?,
+        })
+    }
+}
+
+fn timestamp(t: &Value) -> Option<u64> {
+    // Date.now() is an integer, but some drivers give fractional timestamps
+    t.as_u64().or_else(|| t.as_f64().map(|t| t as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_entries() {
+        let e = LogEntry::from_hook(&json!({
+            "level": "warn",
+            "source": "console",
+            "message": "careful {\"a\":1}",
+            "timestamp": 1500000000000u64,
+        }))
+        .unwrap();
+        assert_eq!(e.level, LogLevel::Warning);
+        assert_eq!(e.source, LogSource::Console);
+        assert_eq!(e.message, "careful {\"a\":1}");
+        assert_eq!(e.timestamp, 1500000000000);
+
+        let e = LogEntry::from_hook(&json!({
+            "level": "error",
+            "source": "rejection",
+            "message": "nope",
+            "timestamp": 1.5,
+        }))
+        .unwrap();
+        assert_eq!(e.source, LogSource::Rejection);
+        assert_eq!(e.timestamp, 1);
+
+        assert!(LogEntry::from_hook(&json!({ "level": "error" })).is_none());
+    }
+
+    #[test]
+    fn legacy_entries() {
+        let e = LogEntry::from_legacy(&json!({
+            "level": "SEVERE",
+            "message": "Uncaught TypeError: x is undefined",
+            "timestamp": 1500000000000u64,
+        }))
+        .unwrap();
+        assert_eq!(e.level, LogLevel::Error);
+        assert_eq!(e.source, LogSource::Browser);
+        assert!(e.level >= LogLevel::Warning);
+
+        assert!(
+            LogEntry::from_legacy(&json!({ "level": "LOUD", "message": "", "timestamp": 0 }))
+                .is_none()
+        );
+    }
+}
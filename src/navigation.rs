@@ -0,0 +1,422 @@
+use error;
+use futures::future::{self, Loop};
+use futures::{Future, IntoFuture};
+use serde_json::Value;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio_core::reactor::Timeout;
+use url;
+use webdriver::error::{ErrorStatus, WebDriverError};
+use {Client, Element, Form, Locator, PageLoadStrategy, PreloadScript};
+
+/// How long to wait between checks for a new document, or for a new title.
+pub(crate) const POLL_INTERVAL: u64 = 50;
+
+/// How long there must be no requests in flight for the network to count as idle.
+const QUIET_PERIOD: u64 = 500;
+
+/// The script that marks the current document, so that it can be told apart from the next one.
+///
+/// It is called with a token unique to this navigation, and returns the URL of the document.
+const MARK: &str = "\
+    document.__fantoccini_navigation = arguments[0];\
+    return document.URL;";
+
+/// The script that checks whether the current document has reached a `LoadState`.
+///
+/// It is called with the token given to `MARK` (or `null`), the name of the state, and the quiet
+/// period for `networkidle`. It returns false while the marked document is still current. The
+/// network is only checked if `NETWORK_TRACKER` has been run in the page.
+const READY: &str = "\
+    var token = arguments[0], state = arguments[1], quiet = arguments[2];\
+    if (token !== null && document.__fantoccini_navigation === token) { return false; }\
+    var ready = document.readyState;\
+    if (state === 'domcontentloaded') { return ready !== 'loading'; }\
+    if (ready !== 'complete') { return false; }\
+    var net = window.__fantoccini_network;\
+    if (state === 'load' || !net) { return true; }\
+    return net.pending === 0 && Date.now() - net.last >= quiet;";
+
+/// The script that counts the `fetch` and `XMLHttpRequest` requests the page has in flight.
+///
+/// It wraps `window.fetch` and `XMLHttpRequest.prototype.send`, and keeps the number of pending
+/// requests, and when that number last changed, in `window.__fantoccini_network`. Running it
+/// again in the same page does nothing.
+const NETWORK_TRACKER: &str = "\
+    if (!window.__fantoccini_network) {\
+        var net = { pending: 0, last: Date.now() };\
+        Object.defineProperty(window, '__fantoccini_network', { value: net });\
+        var start = function() { net.pending++; net.last = Date.now(); };\
+        var done = function() { net.pending = Math.max(0, net.pending - 1); net.last = Date.now(); };\
+        if (window.fetch) {\
+            var fetch = window.fetch;\
+            window.fetch = function() {\
+                start();\
+                var p;\
+                try { p = fetch.apply(this, arguments); } catch (e) { done(); throw e; }\
+                p.then(done, done);\
+                return p;\
+            };\
+        }\
+        if (window.XMLHttpRequest) {\
+            var send = XMLHttpRequest.prototype.send;\
+            XMLHttpRequest.prototype.send = function() {\
+                var settled = false;\
+                var end = function() { if (!settled) { settled = true; done(); } };\
+                this.addEventListener('loadend', end);\
+                start();\
+                try { return send.apply(this, arguments); } catch (e) { end(); throw e; }\
+            };\
+        }\
+    }";
+
+/// The script that starts navigating to `arguments[0]` without waiting for it to load.
+const NAVIGATE: &str = "window.location.assign(arguments[0]);";
+
+/// Used to give every marked document its own token.
+static NEXT_MARK: AtomicUsize = AtomicUsize::new(0);
+
+/// How far a page has loaded.
+///
+/// Pass this to `Client::wait_for_load_state`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum LoadState {
+    /// The HTML has been parsed, and deferred scripts have run (`DOMContentLoaded` has fired).
+    ///
+    /// Images, stylesheets, and frames may still be loading.
+    DomContentLoaded,
+    /// The page and all its resources have loaded (the `load` event has fired).
+    Load,
+    /// The page has loaded, and has not issued any `fetch` or `XMLHttpRequest` requests for
+    /// 500ms.
+    ///
+    /// This is what most single-page apps look like once they have finished rendering.
+    NetworkIdle,
+}
+
+impl LoadState {
+    /// The name `READY` knows this state by.
+    fn name(&self) -> &'static str {
+        match *self {
+            LoadState::DomContentLoaded => "domcontentloaded",
+            LoadState::Load => "load",
+            LoadState::NetworkIdle => "networkidle",
+        }
+    }
+}
+
+/// The document that was current before an action that is expected to navigate away from it.
+struct Mark {
+    token: String,
+    url: url::Url,
+}
+
+impl Client {
+    /// Run `action`, and then wait until it has caused a new page to load.
+    ///
+    /// The current document is marked before `action` runs, and the returned future resolves
+    /// once a different document has replaced it and finished loading (its `readyState` is
+    /// `complete`). Unlike with `wait_for_navigation`, this cannot miss a navigation that
+    /// finishes quickly, and it also notices reloads and navigation to the same URL.
+    ///
+    /// If no new page has loaded within `timeout` of `action` completing,
+    /// `CmdError::NavigationTimeout` is returned; a check that is still waiting for the browser
+    /// when the deadline passes is abandoned, so this happens at most 50ms late. Since the new
+    /// page is a new document, preload scripts are re-injected once it has loaded.
+    ///
+    /// ```rust,no_run
+    /// # extern crate futures;
+    /// # extern crate fantoccini;
+    /// # extern crate tokio_core;
+    /// # use fantoccini::Client;
+    /// # use futures::Future;
+    /// # use std::time::Duration;
+    /// # let mut core = tokio_core::reactor::Core::new().unwrap();
+    /// # let c = Client::new("http://localhost:4444", &core.handle());
+    /// # let c = core.run(c).unwrap();
+    /// // log out with a script, and wait for the login page to come up
+    /// let done = c.expect_navigation(Duration::from_secs(10), |c| {
+    ///     c.execute("document.getElementById('logout').click();", vec![])
+    ///         .map(move |_| c)
+    /// });
+    /// core.run(done).unwrap();
+    /// ```
+    pub fn expect_navigation<F, R>(
+        &self,
+        timeout: Duration,
+        action: F,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static
+    where
+        F: FnOnce(Client) -> R + 'static,
+        R: IntoFuture<Item = Client, Error = error::CmdError> + 'static,
+        R::Future: 'static,
+    {
+        self.expect_navigation_until(LoadState::Load, timeout, action)
+    }
+
+    /// Wait until the current page has loaded as far as `state`.
+    ///
+    /// `goto` and `click` only wait for the `load` event (or less, depending on the session's
+    /// `pageLoadStrategy`), while single-page apps typically keep fetching data and rendering
+    /// long after that. `LoadState::NetworkIdle` waits for those requests to settle as well.
+    ///
+    /// To see the requests it waits for, `LoadState::NetworkIdle` wraps the page's `fetch` and
+    /// `XMLHttpRequest`. Requests that were already in flight when that happened are not seen,
+    /// so call `track_network` first to have every page wrap them as soon as it starts loading.
+    ///
+    /// If the page does not get there within `timeout`, `CmdError::LoadTimeout` is returned,
+    /// at most 50ms late.
+    pub fn wait_for_load_state(
+        &self,
+        state: LoadState,
+        timeout: Duration,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.dup().wait_until_ready(None, state, timeout)
+    }
+
+    /// Keep track of the `fetch` and `XMLHttpRequest` requests of every page this client visits,
+    /// for `LoadState::NetworkIdle`.
+    ///
+    /// The tracker is installed with `preload`, and the returned handle can be passed to
+    /// `remove_preload` to stop tracking new pages. Unless the tracker is preloaded by the browser
+    /// (see `preload`), requests issued while a page is loading are still missed.
+    pub fn track_network(
+        &self,
+    ) -> impl Future<Item = PreloadScript, Error = error::CmdError> + 'static {
+        self.preload(NETWORK_TRACKER)
+    }
+
+    /// Navigate to the given URL from a script, and wait for the new page as `strategy` says.
+    ///
+    /// WebDriver only lets a session pick its `pageLoadStrategy` when it is created, so this does
+    /// not use it. Instead, the navigation is started by setting `window.location`, which returns
+    /// right away, and the waiting is done here, the same way `expect_navigation` does it. With
+    /// `PageLoadStrategy::None`, the returned future resolves as soon as the browser has been told
+    /// to navigate, and preload scripts are not re-injected, since the new page may not exist yet.
+    /// Otherwise, it resolves once the new page has reached `DOMContentLoaded` (`Eager`) or `load`
+    /// (`Normal`), and fails with `CmdError::NavigationTimeout` if that takes longer than
+    /// `timeout`. The session's page load timeout does not apply.
+    ///
+    /// After `PageLoadStrategy::None`, `wait_for_load_state` may still see the old page, which
+    /// has long since loaded, so it cannot be used to wait for the new one. Use `Eager` instead.
+    ///
+    /// Browsers refuse to navigate to some URLs from a script, such as `data:` URLs; use `goto`
+    /// for those.
+    pub fn navigate_with(
+        &self,
+        url: &str,
+        strategy: PageLoadStrategy,
+        timeout: Duration,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let url = url.to_owned();
+        let joined = self
+            .current_url_()
+            .and_then(move |(this, base)| Ok((this, base.join(&url)?)));
+
+        joined.and_then(move |(this, url)| {
+            let navigate = move |this: Client| {
+                this.execute(NAVIGATE, vec![Value::from(url.as_str())])
+                    .map(move |_| this)
+            };
+            let state = match strategy {
+                PageLoadStrategy::None => return future::Either::A(navigate(this)),
+                PageLoadStrategy::Eager => LoadState::DomContentLoaded,
+                PageLoadStrategy::Normal => LoadState::Load,
+            };
+            future::Either::B(this.expect_navigation_until(state, timeout, navigate))
+        })
+    }
+
+    /// Like `expect_navigation`, but wait for the new page to reach `state`.
+    fn expect_navigation_until<F, R>(
+        &self,
+        state: LoadState,
+        timeout: Duration,
+        action: F,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static
+    where
+        F: FnOnce(Client) -> R + 'static,
+        R: IntoFuture<Item = Client, Error = error::CmdError> + 'static,
+        R::Future: 'static,
+    {
+        self.dup()
+            .mark_document()
+            .and_then(move |(this, mark)| action(this).into_future().map(move |this| (this, mark)))
+            .and_then(move |(this, mark)| this.wait_until_ready(Some(mark), state, timeout))
+            .and_then(Client::run_preloads)
+    }
+
+    /// Mark the current document, and note its URL.
+    fn mark_document(self) -> impl Future<Item = (Self, Mark), Error = error::CmdError> + 'static {
+        let token = format!(
+            "{}-{}",
+            process::id(),
+            NEXT_MARK.fetch_add(1, Ordering::Relaxed)
+        );
+        self.execute(MARK, vec![Value::from(token.as_str())])
+            .and_then(move |url| match url {
+                Value::String(url) => {
+                    let url = url::Url::parse(&url)?;
+                    Ok((self, Mark { token, url }))
+                }
+                v => Err(error::CmdError::NotW3C(v)),
+            })
+    }
+
+    /// Poll until the current document has reached `state`.
+    ///
+    /// If `mark` is given, the marked document must also have been replaced.
+    fn wait_until_ready(
+        self,
+        mark: Option<Mark>,
+        state: LoadState,
+        timeout: Duration,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let script = if state == LoadState::NetworkIdle {
+            format!("{}\n{}", NETWORK_TRACKER, READY)
+        } else {
+            READY.to_string()
+        };
+        let token = mark
+            .as_ref()
+            .map(|m| Value::from(m.token.as_str()))
+            .unwrap_or(Value::Null);
+        let deadline = Instant::now() + timeout;
+
+        future::loop_fn(self, move |this| {
+            let timed_out = || match mark {
+                Some(ref mark) => error::CmdError::NavigationTimeout(mark.url.clone()),
+                None => error::CmdError::LoadTimeout(state),
+            };
+            let args = vec![
+                token.clone(),
+                Value::from(state.name()),
+                Value::from(QUIET_PERIOD),
+            ];
+            let check = this.execute(&script, args).then(|r| match r {
+                Ok(ready) => Ok(ready == Value::Bool(true)),
+                Err(ref e) if is_unloading(e) => Ok(false),
+                Err(e) => Err(e),
+            });
+
+            // a page that is busy loading can take a while to answer a script, so don't let
+            // that hold us up past the deadline
+            let now = Instant::now();
+            let left = if deadline > now {
+                deadline - now
+            } else {
+                Duration::from_millis(0)
+            };
+            let expired = timed_out();
+            let expiry = Timeout::new(left, &this.0.handle)
+                .into_future()
+                .flatten()
+                .map_err(error::CmdError::Lost)
+                .and_then(move |_| Err(expired));
+            let timed_out = timed_out();
+
+            check
+                .select(expiry)
+                .map(|(ready, _)| ready)
+                .map_err(|(e, _)| e)
+                .and_then(move |ready| {
+                    if ready {
+                        return future::Either::A(future::ok(Loop::Break(this)));
+                    }
+                    if Instant::now() >= deadline {
+                        return future::Either::A(future::err(timed_out));
+                    }
+
+                    let pause = Timeout::new(Duration::from_millis(POLL_INTERVAL), &this.0.handle);
+                    future::Either::B(
+                        pause
+                            .into_future()
+                            .flatten()
+                            .map_err(error::CmdError::Lost)
+                            .map(move |_| Loop::Continue(this)),
+                    )
+                })
+        })
+    }
+}
+
+impl Element {
+    /// Click on this element, and wait for the click to load a new page.
+    ///
+    /// See `Client::expect_navigation` for how the new page is detected, and what `timeout`
+    /// means.
+    pub fn click_and_wait(
+        self,
+        timeout: Duration,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let c = self.c.dup();
+        c.expect_navigation(timeout, move |_| self.click_())
+    }
+}
+
+impl Form {
+    /// Submit this form using the first available submit button, and wait for the response page
+    /// to load.
+    ///
+    /// See `Client::expect_navigation` for how the new page is detected, and what `timeout`
+    /// means.
+    pub fn submit_and_wait(
+        self,
+        timeout: Duration,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        self.submit_with_and_wait(
+            Locator::Css("input[type=submit],button[type=submit]"),
+            timeout,
+        )
+    }
+
+    /// Submit this form using the button matched by the given selector, and wait for the
+    /// response page to load.
+    ///
+    /// See `Client::expect_navigation` for how the new page is detected, and what `timeout`
+    /// means.
+    pub fn submit_with_and_wait(
+        self,
+        button: Locator,
+        timeout: Duration,
+    ) -> impl Future<Item = Client, Error = error::CmdError> + 'static {
+        let button = button.into();
+        let c = self.c.dup();
+        c.expect_navigation(timeout, move |_| self.submit_by(button))
+    }
+}
+
+/// Returns true if `e` may just mean that the page was being replaced when a script ran.
+pub(crate) fn is_unloading(e: &error::CmdError) -> bool {
+    match *e {
+        error::CmdError::Standard(WebDriverError {
+            error: ErrorStatus::JavascriptError,
+            ..
+        })
+        | error::CmdError::Standard(WebDriverError {
+            error: ErrorStatus::UnknownError,
+            ..
+        })
+        | error::CmdError::Standard(WebDriverError {
+            error: ErrorStatus::ScriptTimeout,
+            ..
+        }) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unloading_errors() {
+        let standard =
+            |status| error::CmdError::Standard(WebDriverError::new(status, "document unloaded"));
+        assert!(is_unloading(&standard(ErrorStatus::JavascriptError)));
+        assert!(is_unloading(&standard(ErrorStatus::UnknownError)));
+        assert!(!is_unloading(&standard(ErrorStatus::NoSuchWindow)));
+        assert!(!is_unloading(&error::CmdError::NotW3C(Value::Null)));
+    }
+}
@@ -0,0 +1,197 @@
+use base64;
+use serde_json;
+use std::io::{self, Cursor, Write};
+use std::process;
+use zip;
+
+/// A handle to a script registered with `Client::preload`.
+///
+/// Pass it to `Client::remove_preload` to stop injecting the script.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct PreloadScript(u64);
+
+/// A registered preload script.
+pub(crate) struct Entry {
+    pub(crate) id: PreloadScript,
+    /// The script, wrapped by `wrap`.
+    pub(crate) source: String,
+    /// How the driver preloads the script for us, if it does.
+    pub(crate) native: Option<Native>,
+}
+
+/// A script that the browser itself runs on every new document.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Native {
+    /// Registered with Chrome's `Page.addScriptToEvaluateOnNewDocument`, under this identifier.
+    Cdp(String),
+    /// Installed into Firefox as a temporary add-on with this id.
+    Addon(String),
+}
+
+/// The preload scripts registered with a `Client`.
+#[derive(Default)]
+pub(crate) struct Registry {
+    next: u64,
+    scripts: Vec<Entry>,
+}
+
+impl Registry {
+    /// Allocate the handle for a new script.
+    pub(crate) fn next_id(&mut self) -> PreloadScript {
+        self.next += 1;
+        PreloadScript(self.next)
+    }
+
+    pub(crate) fn insert(&mut self, entry: Entry) {
+        self.scripts.push(entry);
+    }
+
+    pub(crate) fn remove(&mut self, id: PreloadScript) -> Option<Entry> {
+        let i = self.scripts.iter().position(|e| e.id == id)?;
+        Some(self.scripts.remove(i))
+    }
+
+    /// The script to run after a navigation, if any registered scripts are not preloaded by the
+    /// driver itself.
+    ///
+    /// Scripts run in the order they were registered.
+    pub(crate) fn reinjection(&self) -> Option<String> {
+        let scripts: Vec<_> = self
+            .scripts
+            .iter()
+            .filter(|e| e.native.is_none())
+            .map(|e| &*e.source)
+            .collect();
+        if scripts.is_empty() {
+            None
+        } else {
+            Some(scripts.join("\n"))
+        }
+    }
+}
+
+/// Wrap `source` so that it runs at most once per document.
+///
+/// Scripts are re-injected after anything that *may* have navigated, such as a click, so the same
+/// document may see a script many times. The guard is stored as a non-enumerable property on
+/// `window` so that it does not show up when pages inspect the global object.
+pub(crate) fn wrap(id: PreloadScript, source: &str) -> String {
+    format!(
+        "(function() {{\
+         var key = '__fantoccini_preload_{}';\
+         if (Object.prototype.hasOwnProperty.call(window, key)) {{ return; }}\
+         Object.defineProperty(window, key, {{ value: true }});\n\
+         {}\n\
+         }}).call(window);",
+        id.0, source
+    )
+}
+
+/// Package `source` (as returned by `wrap`) as a Firefox add-on, encoded as base64.
+///
+/// The add-on has a content script that runs at `document_start` in every frame. Content scripts
+/// are isolated from the page, so it runs `source` by inserting it as an inline `<script>`, which
+/// pages whose Content Security Policy forbids inline scripts will block.
+pub(crate) fn addon(id: PreloadScript, source: &str) -> io::Result<String> {
+    let manifest = json!({
+        "manifest_version": 2,
+        "name": format!("fantoccini preload {}", id.0),
+        "version": "1.0",
+        "applications": {
+            "gecko": { "id": format!("preload-{}-{}@fantoccini", process::id(), id.0) }
+        },
+        "content_scripts": [{
+            "matches": ["<all_urls>"],
+            "js": ["preload.js"],
+            "run_at": "document_start",
+            "all_frames": true
+        }]
+    });
+    let script = format!(
+        "(function() {{\
+         var s = document.createElement('script');\
+         s.textContent = {};\
+         (document.head || document.documentElement).appendChild(s);\
+         s.remove();\
+         }})();",
+        serde_json::to_string(source)?
+    );
+
+    let mut xpi = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    xpi.start_file("manifest.json", options)?;
+    xpi.write_all(manifest.to_string().as_bytes())?;
+    xpi.start_file("preload.js", options)?;
+    xpi.write_all(script.as_bytes())?;
+    let xpi = xpi.finish()?.into_inner();
+    Ok(base64::encode(&xpi))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reinjection_skips_native_scripts() {
+        let mut r = Registry::default();
+        assert_eq!(r.reinjection(), None);
+
+        let a = r.next_id();
+        let b = r.next_id();
+        let c = r.next_id();
+        assert!(a != b && b != c);
+        r.insert(Entry {
+            id: a,
+            source: "a()".to_string(),
+            native: None,
+        });
+        r.insert(Entry {
+            id: b,
+            source: "b()".to_string(),
+            native: Some(Native::Cdp("1".to_string())),
+        });
+        r.insert(Entry {
+            id: c,
+            source: "c()".to_string(),
+            native: None,
+        });
+        assert_eq!(r.reinjection(), Some("a()\nc()".to_string()));
+
+        assert_eq!(r.remove(a).map(|e| e.source), Some("a()".to_string()));
+        assert!(r.remove(a).is_none());
+        assert_eq!(r.reinjection(), Some("c()".to_string()));
+    }
+
+    #[test]
+    fn wrapped_scripts_are_guarded() {
+        let w = wrap(PreloadScript(7), "window.x = 1;");
+        assert!(w.contains("'__fantoccini_preload_7'"));
+        assert!(w.contains("\nwindow.x = 1;\n"));
+    }
+
+    #[test]
+    fn addons_run_the_script() {
+        use std::io::Read;
+
+        let xpi = base64::decode(&addon(PreloadScript(3), "window.x = '\"';").unwrap()).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(xpi)).unwrap();
+
+        let mut manifest = String::new();
+        archive
+            .by_name("manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["content_scripts"][0]["run_at"], "document_start");
+        assert_eq!(manifest["content_scripts"][0]["js"][0], "preload.js");
+
+        let mut script = String::new();
+        archive
+            .by_name("preload.js")
+            .unwrap()
+            .read_to_string(&mut script)
+            .unwrap();
+        assert!(script.contains(r#"s.textContent = "window.x = '\"';";"#));
+    }
+}
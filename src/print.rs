@@ -0,0 +1,254 @@
+use error;
+use serde_json::Value;
+
+/// The orientation of printed pages.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum PrintOrientation {
+    /// Pages are taller than they are wide.
+    Portrait,
+    /// Pages are wider than they are tall.
+    Landscape,
+}
+
+impl Default for PrintOrientation {
+    fn default() -> Self {
+        PrintOrientation::Portrait
+    }
+}
+
+/// The size of a printed page, in centimeters.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PrintPageSize {
+    /// The width of the page.
+    pub width: f64,
+    /// The height of the page.
+    pub height: f64,
+}
+
+impl PrintPageSize {
+    /// US Letter, which is the default for printing through WebDriver.
+    pub const LETTER: PrintPageSize = PrintPageSize {
+        width: 21.59,
+        height: 27.94,
+    };
+
+    /// ISO A4.
+    pub const A4: PrintPageSize = PrintPageSize {
+        width: 21.0,
+        height: 29.7,
+    };
+}
+
+impl Default for PrintPageSize {
+    fn default() -> Self {
+        PrintPageSize::LETTER
+    }
+}
+
+/// The margins of a printed page, in centimeters.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PrintMargins {
+    /// The top margin.
+    pub top: f64,
+    /// The bottom margin.
+    pub bottom: f64,
+    /// The left margin.
+    pub left: f64,
+    /// The right margin.
+    pub right: f64,
+}
+
+impl Default for PrintMargins {
+    fn default() -> Self {
+        PrintMargins {
+            top: 1.0,
+            bottom: 1.0,
+            left: 1.0,
+            right: 1.0,
+        }
+    }
+}
+
+/// Options for `Client::print`.
+///
+/// The defaults match those of the [WebDriver print command].
+///
+/// [WebDriver print command]: https://www.w3.org/TR/webdriver2/#print-page
+#[derive(Clone, PartialEq, Debug)]
+pub struct PrintOptions {
+    /// The orientation of the pages.
+    pub orientation: PrintOrientation,
+    /// How much to scale the page contents. Must be between 0.1 and 2.
+    pub scale: f64,
+    /// Whether to print background colors and images.
+    pub background: bool,
+    /// The size of each page.
+    pub page: PrintPageSize,
+    /// The margins of each page.
+    pub margins: PrintMargins,
+    /// Whether to shrink the contents to fit the page width.
+    pub shrink_to_fit: bool,
+    /// Which pages to print, such as `"1"` or `"3-5"`.
+    ///
+    /// All pages are printed if this is empty.
+    pub page_ranges: Vec<String>,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            orientation: PrintOrientation::default(),
+            scale: 1.0,
+            background: false,
+            page: PrintPageSize::default(),
+            margins: PrintMargins::default(),
+            shrink_to_fit: true,
+            page_ranges: Vec::new(),
+        }
+    }
+}
+
+impl PrintOptions {
+    /// Check that these options are within the bounds required by the spec.
+    pub(crate) fn validate(&self) -> Result<(), error::CmdError> {
+        fn invalid(arg: &str, expected: &str, was: f64) -> Result<(), error::CmdError> {
+            Err(error::CmdError::InvalidArgument(
+                arg.to_string(),
+                format!("Expected to be {} but was `{}`", expected, was),
+            ))
+        }
+
+        if self.scale.is_nan() || self.scale < 0.1 || self.scale > 2.0 {
+            return invalid("scale", "between `0.1` and `2`", self.scale);
+        }
+
+        // the spec requires pages to be at least one point in each direction
+        let min = 2.54 / 72.0;
+        if self.page.width.is_nan() || self.page.width < min {
+            return invalid("page.width", "at least one point", self.page.width);
+        }
+        if self.page.height.is_nan() || self.page.height < min {
+            return invalid("page.height", "at least one point", self.page.height);
+        }
+
+        let margins = [
+            ("margins.top", self.margins.top),
+            ("margins.bottom", self.margins.bottom),
+            ("margins.left", self.margins.left),
+            ("margins.right", self.margins.right),
+        ];
+        for &(arg, margin) in &margins {
+            if margin.is_nan() || margin < 0.0 {
+                return invalid(arg, "`>= 0`", margin);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize these options as the parameters of the print command.
+    pub(crate) fn to_json(&self) -> Value {
+        let orientation = match self.orientation {
+            PrintOrientation::Portrait => "portrait",
+            PrintOrientation::Landscape => "landscape",
+        };
+
+        json!({
+            "orientation": orientation,
+            "scale": self.scale,
+            "background": self.background,
+            "page": {
+                "width": self.page.width,
+                "height": self.page.height,
+            },
+            "margin": {
+                "top": self.margins.top,
+                "bottom": self.margins.bottom,
+                "left": self.margins.left,
+                "right": self.margins.right,
+            },
+            "shrinkToFit": self.shrink_to_fit,
+            "pageRanges": self.page_ranges,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_options() {
+        let json = PrintOptions::default().to_json();
+        assert_eq!(json["orientation"], "portrait");
+        assert_eq!(json["scale"], 1.0);
+        assert_eq!(json["background"], false);
+        assert_eq!(json["shrinkToFit"], true);
+        assert_eq!(json["pageRanges"], json!([]));
+        // the spec measures pages and margins in centimeters too, so nothing is converted
+        assert_eq!(json["page"], json!({ "width": 21.59, "height": 27.94 }));
+        assert_eq!(
+            json["margin"],
+            json!({ "top": 1.0, "bottom": 1.0, "left": 1.0, "right": 1.0 })
+        );
+    }
+
+    #[test]
+    fn custom_options() {
+        let options = PrintOptions {
+            orientation: PrintOrientation::Landscape,
+            page: PrintPageSize::A4,
+            margins: PrintMargins {
+                top: 0.0,
+                bottom: 2.5,
+                left: 0.5,
+                right: 0.5,
+            },
+            page_ranges: vec!["1".to_string(), "3-5".to_string()],
+            ..PrintOptions::default()
+        };
+        let json = options.to_json();
+        assert_eq!(json["orientation"], "landscape");
+        assert_eq!(json["page"], json!({ "width": 21.0, "height": 29.7 }));
+        assert_eq!(
+            json["margin"],
+            json!({ "top": 0.0, "bottom": 2.5, "left": 0.5, "right": 0.5 })
+        );
+        assert_eq!(json["pageRanges"], json!(["1", "3-5"]));
+    }
+
+    #[test]
+    fn out_of_bounds_options() {
+        let invalid = |options: PrintOptions| match options.validate() {
+            Err(error::CmdError::InvalidArgument(arg, _)) => arg,
+            r => panic!("unexpected result: {:?}", r),
+        };
+
+        assert!(PrintOptions::default().validate().is_ok());
+        let scaled = |scale| PrintOptions {
+            scale,
+            ..PrintOptions::default()
+        };
+        assert!(scaled(0.1).validate().is_ok());
+        assert!(scaled(2.0).validate().is_ok());
+        assert_eq!(invalid(scaled(0.05)), "scale");
+        assert_eq!(invalid(scaled(2.5)), "scale");
+        assert_eq!(invalid(scaled(::std::f64::NAN)), "scale");
+
+        let sized = |width, height| PrintOptions {
+            page: PrintPageSize { width, height },
+            ..PrintOptions::default()
+        };
+        assert_eq!(invalid(sized(0.0, 10.0)), "page.width");
+        assert_eq!(invalid(sized(10.0, 0.01)), "page.height");
+
+        let margins = PrintOptions {
+            margins: PrintMargins {
+                left: -1.0,
+                ..PrintMargins::default()
+            },
+            ..PrintOptions::default()
+        };
+        assert_eq!(invalid(margins), "margins.left");
+    }
+}
@@ -0,0 +1,266 @@
+use error;
+use serde;
+use serde_json::{self, Value};
+use serde_path_to_error;
+use std::collections::BTreeMap;
+use webdriver::common::{WebElement, ELEMENT_KEY};
+use {Client, Element};
+
+/// A value returned by a script run through `Client::execute_with_elements` or
+/// `Client::execute_async_with_elements`.
+///
+/// This mirrors the JSON returned by the WebDriver server, except that any web element references
+/// (including those nested in arrays and objects) have been turned into `Element`s that can be
+/// interacted with directly.
+pub enum ScriptValue {
+    /// `null` or `undefined`.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// A number.
+    Number(serde_json::Number),
+    /// A string.
+    String(String),
+    /// An array or other collection, such as a `NodeList`.
+    Array(Vec<ScriptValue>),
+    /// An object.
+    Object(BTreeMap<String, ScriptValue>),
+    /// A DOM element.
+    Element(Element),
+}
+
+impl ScriptValue {
+    /// Convert the JSON value `v` returned from a script, turning web element references into
+    /// `Element`s bound to `c`.
+    pub(crate) fn from_json(c: &Client, v: Value) -> Self {
+        match v {
+            Value::Null => ScriptValue::Null,
+            Value::Bool(b) => ScriptValue::Bool(b),
+            Value::Number(n) => ScriptValue::Number(n),
+            Value::String(s) => ScriptValue::String(s),
+            Value::Array(vs) => {
+                ScriptValue::Array(vs.into_iter().map(|v| Self::from_json(c, v)).collect())
+            }
+            Value::Object(o) => {
+                if let Some(id) = element_id(&o, c.0.legacy) {
+                    return ScriptValue::Element(Element {
+                        c: c.dup(),
+                        e: WebElement::new(id.to_string()),
+                    });
+                }
+
+                ScriptValue::Object(
+                    o.into_iter()
+                        .map(|(k, v)| (k, Self::from_json(c, v)))
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Returns true if this value is `null`.
+    pub fn is_null(&self) -> bool {
+        if let ScriptValue::Null = *self {
+            true
+        } else {
+            false
+        }
+    }
+
+    /// If this value is a boolean, return it.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            ScriptValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// If this value is an integer that fits in an `i64`, return it.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            ScriptValue::Number(ref n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    /// If this value is a non-negative integer, return it.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            ScriptValue::Number(ref n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    /// If this value is a number, return it as an `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            ScriptValue::Number(ref n) => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    /// If this value is a string, return it.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            ScriptValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// If this value is an array, return its items.
+    pub fn as_array(&self) -> Option<&[ScriptValue]> {
+        match *self {
+            ScriptValue::Array(ref vs) => Some(vs),
+            _ => None,
+        }
+    }
+
+    /// If this value is an object, return its fields.
+    pub fn as_object(&self) -> Option<&BTreeMap<String, ScriptValue>> {
+        match *self {
+            ScriptValue::Object(ref o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// If this value is an element, return it.
+    pub fn into_element(self) -> Option<Element> {
+        match self {
+            ScriptValue::Element(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// If this value is an array, return its items.
+    pub fn into_array(self) -> Option<Vec<ScriptValue>> {
+        match self {
+            ScriptValue::Array(vs) => Some(vs),
+            _ => None,
+        }
+    }
+
+    /// Collect all the elements in this array.
+    ///
+    /// Returns `None` if this value is not an array, or if any of its items is not an element.
+    /// This is handy for scripts that return a list of nodes, such as from `querySelectorAll`.
+    pub fn into_elements(self) -> Option<Vec<Element>> {
+        self.into_array()?
+            .into_iter()
+            .map(ScriptValue::into_element)
+            .collect()
+    }
+}
+
+impl serde::Serialize for ScriptValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            ScriptValue::Null => serializer.serialize_unit(),
+            ScriptValue::Bool(b) => serializer.serialize_bool(b),
+            ScriptValue::Number(ref n) => n.serialize(serializer),
+            ScriptValue::String(ref s) => serializer.serialize_str(s),
+            ScriptValue::Array(ref vs) => vs.serialize(serializer),
+            ScriptValue::Object(ref o) => o.serialize(serializer),
+            ScriptValue::Element(ref e) => e.serialize(serializer),
+        }
+    }
+}
+
+/// Serialize `args` into the list of arguments for a script.
+///
+/// `args` must serialize to an array; the unit type `()` is taken to mean no arguments.
+pub(crate) fn args_to_json<A: serde::Serialize>(args: &A) -> Result<Vec<Value>, error::CmdError> {
+    let invalid = |msg: String| error::CmdError::InvalidArgument("args".to_string(), msg);
+
+    match serde_json::to_value(args) {
+        Ok(Value::Array(args)) => Ok(args),
+        Ok(Value::Null) => Ok(Vec::new()),
+        Ok(v) => Err(invalid(format!(
+            "Expected to serialize to an array but was `{}`",
+            v
+        ))),
+        Err(e) => Err(invalid(e.to_string())),
+    }
+}
+
+/// Deserialize the value `v` returned by a script into an `R`.
+pub(crate) fn from_json<R: serde::de::DeserializeOwned>(v: Value) -> Result<R, error::CmdError> {
+    serde_path_to_error::deserialize(v)
+        .map_err(|e| error::CmdError::TypeMismatch(e.path().to_string(), e.into_inner()))
+}
+
+/// If the JSON object `o` is a web element reference, return the element's id.
+///
+/// Some drivers include both the W3C and the legacy key in references, so the other key is
+/// allowed to be present as well.
+fn element_id(o: &serde_json::Map<String, Value>, legacy: bool) -> Option<&str> {
+    // legacy protocol uses "ELEMENT" as identifier
+    let (key, other) = if legacy {
+        ("ELEMENT", ELEMENT_KEY)
+    } else {
+        (ELEMENT_KEY, "ELEMENT")
+    };
+
+    let id = o.get(key)?.as_str()?;
+    if o.keys().all(|k| k == key || k == other) {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obj(fields: &[(&str, &str)]) -> serde_json::Map<String, Value> {
+        fields
+            .iter()
+            .map(|&(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn element_references() {
+        assert_eq!(element_id(&obj(&[(ELEMENT_KEY, "a")]), false), Some("a"));
+        assert_eq!(element_id(&obj(&[("ELEMENT", "a")]), true), Some("a"));
+        assert_eq!(
+            element_id(&obj(&[(ELEMENT_KEY, "a"), ("ELEMENT", "a")]), false),
+            Some("a")
+        );
+
+        // wrong key for the protocol in use
+        assert_eq!(element_id(&obj(&[("ELEMENT", "a")]), false), None);
+        // regular objects that just happen to have the key
+        assert_eq!(
+            element_id(&obj(&[(ELEMENT_KEY, "a"), ("name", "b")]), false),
+            None
+        );
+
+        let mut o = serde_json::Map::new();
+        o.insert(ELEMENT_KEY.to_string(), json!(1));
+        assert_eq!(element_id(&o, false), None);
+    }
+
+    #[test]
+    fn typed_args() {
+        assert!(args_to_json(&()).unwrap().is_empty());
+        assert_eq!(args_to_json(&("a", 1)).unwrap(), vec![json!("a"), json!(1)]);
+        match args_to_json(&"a") {
+            Err(error::CmdError::InvalidArgument(ref arg, _)) => assert_eq!(arg, "args"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn typed_results() {
+        use std::collections::HashMap;
+
+        let v = json!({ "rows": [[1, 2], [3, "four"]] });
+        let rows: Vec<Vec<u32>> = from_json(json!([[1, 2], [3]])).unwrap();
+        assert_eq!(rows, vec![vec![1, 2], vec![3]]);
+        match from_json::<HashMap<String, Vec<Vec<u32>>>>(v) {
+            Err(error::CmdError::TypeMismatch(ref path, _)) => assert_eq!(path, "rows[1][1]"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
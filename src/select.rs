@@ -0,0 +1,272 @@
+use error;
+use futures::Future;
+use serde_json::Value;
+use webdriver::common::WebElement;
+use webdriver::error::{ErrorStatus, WebDriverError};
+use {element_ref, Client, Element};
+
+/// The script that selects or deselects the matching options of a `<select>`.
+///
+/// It is called with the element, how to match options (`value`, `text`, `index`, or `all`), what
+/// to match, and whether to select or deselect. It returns how many options matched. `input` and
+/// `change` events are fired if anything changed, just like when the user picks an option.
+const SET: &str = "\
+    var s = arguments[0], by = arguments[1], key = arguments[2], on = arguments[3];\
+    if (!s || s.tagName !== 'SELECT') { throw new Error('element is not a <select>'); }\
+    if (!on && !s.multiple) {\
+        throw new Error('options can only be deselected in a <select multiple>');\
+    }\
+    var text = function(o) { return o.text.replace(/\\s+/g, ' ').trim(); };\
+    var disabled = function(o) {\
+        var g = o.parentNode;\
+        return o.disabled || (!!g && g.tagName === 'OPTGROUP' && g.disabled);\
+    };\
+    var matched = 0, changed = false;\
+    for (var i = 0; i < s.options.length; i++) {\
+        var o = s.options[i];\
+        var hit = by === 'all' || (by === 'value' && o.value === key) ||\
+            (by === 'index' && o.index === key) || (by === 'text' && text(o) === key);\
+        if (!hit) { continue; }\
+        if (on && disabled(o)) { throw new Error('option ' + o.index + ' is disabled'); }\
+        matched++;\
+        if (o.selected !== on) { o.selected = on; changed = true; }\
+        if (on && !s.multiple) { break; }\
+    }\
+    if (changed) {\
+        s.dispatchEvent(new Event('input', { bubbles: true }));\
+        s.dispatchEvent(new Event('change', { bubbles: true }));\
+    }\
+    return matched;";
+
+/// The script that lists the options of a `<select>`.
+const OPTIONS: &str = "\
+    var s = arguments[0];\
+    if (!s || s.tagName !== 'SELECT') { throw new Error('element is not a <select>'); }\
+    var disabled = function(o) {\
+        var g = o.parentNode;\
+        return o.disabled || (!!g && g.tagName === 'OPTGROUP' && g.disabled);\
+    };\
+    var options = [];\
+    for (var i = 0; i < s.options.length; i++) {\
+        var o = s.options[i];\
+        options.push({\
+            index: o.index,\
+            value: o.value,\
+            text: o.text.replace(/\\s+/g, ' ').trim(),\
+            selected: o.selected,\
+            disabled: disabled(o)\
+        });\
+    }\
+    return options;";
+
+/// An option of a `<select>` element.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SelectOption {
+    /// The position of the option in the list, counting from 0.
+    pub index: usize,
+    /// The value submitted with the form when the option is selected.
+    pub value: String,
+    /// The text the user sees, with whitespace collapsed.
+    pub text: String,
+    /// Whether the option is currently selected.
+    pub selected: bool,
+    /// Whether the option is disabled, either by itself or by its `<optgroup>`.
+    pub disabled: bool,
+}
+
+impl SelectOption {
+    /// Parse an option as returned by `OPTIONS`.
+    fn from_json(o: &Value) -> Option<Self> {
+        Some(SelectOption {
+            index: o.get("index")?.as_u64()? as usize,
+            value: o.get("value")?.as_str()?.to_string(),
+            text: o.get("text")?.as_str()?.to_string(),
+            selected: o.get("selected")?.as_bool()?,
+            disabled: o.get("disabled")?.as_bool()?,
+        })
+    }
+}
+
+/// A `<select>` element on the current page.
+///
+/// Obtained with `Element::into_select`. Options are changed with JavaScript rather than by
+/// clicking on them, so this also works for drop-downs that the browser draws itself. `input`
+/// and `change` events are fired whenever the selection changes.
+pub struct Select {
+    c: Client,
+    s: WebElement,
+}
+
+impl Element {
+    /// Treat this element as a `<select>`.
+    ///
+    /// If the element is not a `<select>`, the methods of the returned `Select` will fail.
+    pub fn into_select(self) -> Select {
+        Select {
+            c: self.c,
+            s: self.e,
+        }
+    }
+}
+
+impl Select {
+    /// Select the option with the given `value`.
+    ///
+    /// In a `<select multiple>`, all options with that value are selected, and already selected
+    /// options stay selected.
+    pub fn select_by_value(
+        &self,
+        value: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("value", Value::from(value), true)
+    }
+
+    /// Select the option whose text is `text`.
+    ///
+    /// Whitespace in the option text is collapsed before comparing. In a `<select multiple>`, all
+    /// options with that text are selected.
+    pub fn select_by_visible_text(
+        &self,
+        text: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("text", Value::from(text), true)
+    }
+
+    /// Select the option at the given `index`, counting from 0.
+    pub fn select_by_index(
+        &self,
+        index: usize,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("index", Value::from(index), true)
+    }
+
+    /// Deselect all options with the given `value`.
+    ///
+    /// Only options of a `<select multiple>` can be deselected.
+    pub fn deselect_by_value(
+        &self,
+        value: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("value", Value::from(value), false)
+    }
+
+    /// Deselect all options whose text is `text`.
+    ///
+    /// Only options of a `<select multiple>` can be deselected.
+    pub fn deselect_by_visible_text(
+        &self,
+        text: &str,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("text", Value::from(text), false)
+    }
+
+    /// Deselect the option at the given `index`, counting from 0.
+    ///
+    /// Only options of a `<select multiple>` can be deselected.
+    pub fn deselect_by_index(
+        &self,
+        index: usize,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("index", Value::from(index), false)
+    }
+
+    /// Deselect all options.
+    ///
+    /// Only options of a `<select multiple>` can be deselected.
+    pub fn deselect_all(&self) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.set("all", Value::Null, false)
+    }
+
+    /// List all the options of this `<select>`, in order.
+    pub fn options(
+        &self,
+    ) -> impl Future<Item = Vec<SelectOption>, Error = error::CmdError> + 'static {
+        self.c
+            .execute(OPTIONS, vec![element_ref(&self.s)])
+            .and_then(|options| {
+                let parsed = match options.as_array() {
+                    Some(os) => os
+                        .iter()
+                        .map(SelectOption::from_json)
+                        .collect::<Option<Vec<_>>>(),
+                    None => None,
+                };
+
+                parsed.ok_or_else(|| error::CmdError::NotW3C(options))
+            })
+    }
+
+    /// List the options that are currently selected, in order.
+    pub fn selected_options(
+        &self,
+    ) -> impl Future<Item = Vec<SelectOption>, Error = error::CmdError> + 'static {
+        self.options()
+            .map(|options| options.into_iter().filter(|o| o.selected).collect())
+    }
+
+    /// Get back the underlying `<select>` element.
+    pub fn into_element(self) -> Element {
+        Element {
+            c: self.c,
+            e: self.s,
+        }
+    }
+
+    fn set(
+        &self,
+        by: &str,
+        key: Value,
+        select: bool,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        let miss = match key {
+            Value::Null => None,
+            Value::String(ref s) => Some(format!("no option with {} `{}`", by, s)),
+            ref k => Some(format!("no option with {} `{}`", by, k)),
+        };
+        let args = vec![
+            element_ref(&self.s),
+            Value::from(by),
+            key,
+            Value::Bool(select),
+        ];
+        let this = Select {
+            c: self.c.dup(),
+            s: self.s.clone(),
+        };
+
+        self.c
+            .execute(SET, args)
+            .and_then(move |matched| match (matched.as_u64(), miss) {
+                (Some(0), Some(miss)) => Err(error::CmdError::NoSuchElement(WebDriverError::new(
+                    ErrorStatus::NoSuchElement,
+                    miss,
+                ))),
+                (Some(_), _) => Ok(this),
+                (None, _) => Err(error::CmdError::NotW3C(matched)),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_options() {
+        let o = SelectOption::from_json(&json!({
+            "index": 2,
+            "value": "de",
+            "text": "Deutsch",
+            "selected": true,
+            "disabled": false,
+        }))
+        .unwrap();
+        assert_eq!(o.index, 2);
+        assert_eq!(o.value, "de");
+        assert_eq!(o.text, "Deutsch");
+        assert!(o.selected);
+        assert!(!o.disabled);
+
+        assert!(SelectOption::from_json(&json!({ "index": 0, "value": "de" })).is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use base64;
+use error;
+use futures::{future, stream, Future, Stream};
+use hyper::Method;
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+use webdriver::command::{SendKeysParameters, WebDriverCommand};
+use webdriver::error::{ErrorStatus, WebDriverError};
+use zip;
+use {Client, Element, RawCmd};
+
+impl Element {
+    /// Choose the file at `path` in this file input.
+    ///
+    /// See `upload_files`.
+    pub fn upload_file<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static {
+        self.upload_files(Some(path))
+    }
+
+    /// Choose the files at the given `paths` in this file input.
+    ///
+    /// Choosing more than one file only works if the input has the `multiple` attribute.
+    ///
+    /// Each file is first sent to the WebDriver server through the Selenium file upload endpoint,
+    /// and the browser is given the paths the server stored the files at. If the server does not
+    /// support that endpoint, the browser is given the local paths instead, which only works if
+    /// the server runs on this machine. Use `Client::set_remote_uploads` to pick either behavior
+    /// up front.
+    ///
+    /// Files that are sent to the server are read into memory whole, and that read blocks the
+    /// event loop the returned future runs on. For large files, prefer local uploads where
+    /// possible.
+    pub fn upload_files<I, P>(
+        self,
+        paths: I,
+    ) -> impl Future<Item = Self, Error = error::CmdError> + 'static
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let paths: Result<Vec<_>, _> = paths
+            .into_iter()
+            .map(|p| fs::canonicalize(p.as_ref()))
+            .collect();
+        let paths = match paths {
+            Ok(ref paths) if paths.is_empty() => {
+                return future::Either::B(future::err(error::CmdError::InvalidArgument(
+                    "paths".to_string(),
+                    "Expected to contain at least one path but was empty".to_string(),
+                )))
+            }
+            Ok(paths) => paths,
+            Err(e) => return future::Either::B(future::err(error::CmdError::FileSystem(e))),
+        };
+
+        let remote = if self.c.0.remote_uploads.get() == Some(false) {
+            future::Either::A(future::ok(None))
+        } else {
+            let c = self.c.dup();
+            let fallback = self.c.0.remote_uploads.get().is_none();
+            let record = self.c.dup();
+            future::Either::B(
+                stream::iter_ok(paths.clone())
+                    .and_then(move |path| c.upload(&path))
+                    .collect()
+                    .map(Some)
+                    .or_else(move |e| match e {
+                        error::CmdError::Standard(WebDriverError {
+                            error: ErrorStatus::UnknownCommand,
+                            ..
+                        }) if fallback => {
+                            // don't send the files again just to be told no
+                            record.0.remote_uploads.set(Some(false));
+                            Ok(None)
+                        }
+                        e => Err(e),
+                    }),
+            )
+        };
+
+        future::Either::A(remote.and_then(move |remote| {
+            let paths = remote.unwrap_or_else(|| {
+                paths
+                    .iter()
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .collect()
+            });
+            let cmd = WebDriverCommand::ElementSendKeys(
+                self.e.clone(),
+                SendKeysParameters {
+                    text: paths.join("\n"),
+                },
+            );
+            self.c.dup().issue_wd_cmd(cmd).map(move |_| self)
+        }))
+    }
+}
+
+impl Client {
+    /// Choose how `Element::upload_files` hands files to the browser.
+    ///
+    /// With `true`, files are always sent to the WebDriver server first, and uploading fails if
+    /// the server does not support that. With `false`, the browser is always given local paths,
+    /// which is what a WebDriver server running on this machine needs, and saves copying the files.
+    pub fn set_remote_uploads(&self, remote: bool) {
+        self.0.remote_uploads.set(Some(remote));
+    }
+
+    /// Send the file at `path` to the WebDriver server, and return where the server stored it.
+    fn upload(&self, path: &Path) -> impl Future<Item = String, Error = error::CmdError> + 'static {
+        let file = match zip_file(path) {
+            Ok(file) => file,
+            Err(e) => return future::Either::B(future::err(error::CmdError::FileSystem(e))),
+        };
+
+        let cmd = RawCmd {
+            method: Method::Post,
+            path: if self.0.legacy { "file" } else { "se/file" }.to_string(),
+            body: Some(json!({ "file": file })),
+        };
+        future::Either::A(
+            self.dup()
+                .issue_wd_cmd(WebDriverCommand::Extension(cmd))
+                .and_then(|(_, v)| match v {
+                    Value::String(path) => Ok(path),
+                    v => Err(error::CmdError::NotW3C(v)),
+                }),
+        )
+    }
+}
+
+/// Read the file at `path` into a zip archive of its own, encoded as base64.
+///
+/// This is the format the Selenium upload endpoint expects. The file is stored uncompressed, since
+/// most uploads (such as images) are compressed already; the base64 encoding still adds a third.
+fn zip_file(path: &Path) -> io::Result<String> {
+    let contents = fs::read(path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "upload".to_string());
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file(name, options)?;
+    zip.write_all(&contents)?;
+    let zip = zip.finish()?.into_inner();
+    Ok(base64::encode(&zip))
+}
+
+/// A file in the system temporary directory, for tests.
+#[cfg(test)]
+pub(crate) struct TempFile(pub(crate) ::std::path::PathBuf);
+
+#[cfg(test)]
+impl TempFile {
+    pub(crate) fn new(name: &str, contents: &[u8]) -> Self {
+        let path =
+            ::std::env::temp_dir().join(format!("fantoccini-{}-{}", ::std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn zipped_files() {
+        let file = TempFile::new("hello.txt", b"hello world");
+        let zip = base64::decode(&zip_file(&file.0).unwrap()).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), 1);
+        let mut entry = archive.by_index(0).unwrap();
+        assert!(entry.name().ends_with("hello.txt"));
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello world");
+    }
+}
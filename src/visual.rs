@@ -0,0 +1,347 @@
+//! Visual regression testing against stored baseline images.
+//!
+//! Take a screenshot with `Client::screenshot` (or one of its siblings), and then compare it
+//! against a baseline PNG that is checked in alongside your tests:
+//!
+//! ```no_run
+//! # extern crate tokio_core;
+//! # extern crate futures;
+//! # extern crate fantoccini;
+//! # fn main() {
+//! use fantoccini::Client;
+//! use fantoccini::visual::{self, Options, Region};
+//! use futures::future::Future;
+//! let mut core = tokio_core::reactor::Core::new().unwrap();
+//! let c = core.run(Client::new("http://localhost:4444", &core.handle())).unwrap();
+//! let png = core.run(c.goto("https://www.wikipedia.org/").and_then(|c| c.screenshot()))
+//!     .unwrap();
+//!
+//! let mut options = Options::default();
+//! // ignore the ever-changing banner at the top
+//! options.ignore.push(Region { x: 0, y: 0, width: 1024, height: 80 });
+//! options.diff_path = Some("target/wikipedia.diff.png".into());
+//! let result = visual::compare(&png, "tests/baselines/wikipedia.png", &options).unwrap();
+//! assert!(result.is_match(), "{} pixels differ", result.differing_pixels);
+//! # }
+//! ```
+//!
+//! When the environment variable named by `UPDATE_BASELINES_VAR` is set (to anything other than
+//! `0`), `compare` instead overwrites the baseline with the given image, and reports a match. Run
+//! your tests once with it set after an intended layout change.
+
+use error::{ImageError, VisualError};
+use image::Image;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The environment variable that, when set, makes `compare` update baselines instead of
+/// comparing against them.
+pub const UPDATE_BASELINES_VAR: &str = "FANTOCCINI_UPDATE_BASELINES";
+
+/// A rectangular region of an image, in pixels.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Region {
+    /// The column of the region's left edge.
+    pub x: u32,
+    /// The row of the region's top edge.
+    pub y: u32,
+    /// The width of the region.
+    pub width: u32,
+    /// The height of the region.
+    pub height: u32,
+}
+
+impl Region {
+    fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && u64::from(x) < u64::from(self.x) + u64::from(self.width)
+            && u64::from(y) < u64::from(self.y) + u64::from(self.height)
+    }
+}
+
+/// Options for `compare`.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Options {
+    /// How much any one color channel of a pixel may differ before the pixel is considered
+    /// different.
+    ///
+    /// This absorbs small rendering differences, such as those from anti-aliasing.
+    pub tolerance: u8,
+
+    /// Regions of the image to ignore, such as ones that show the current time.
+    pub ignore: Vec<Region>,
+
+    /// Where to write an image highlighting the differences, if any are found.
+    pub diff_path: Option<PathBuf>,
+}
+
+/// The result of comparing an image against a baseline.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Comparison {
+    /// The dimensions (width, height) of the compared image.
+    pub actual_size: (u32, u32),
+    /// The dimensions (width, height) of the baseline, if one was compared against.
+    pub baseline_size: Option<(u32, u32)>,
+    /// The number of pixels that were compared (that is, that were not ignored).
+    pub compared_pixels: u64,
+    /// The number of compared pixels that differed by more than the tolerance.
+    ///
+    /// If the two images are of different sizes, pixels that are only present in one of them
+    /// count as differing.
+    pub differing_pixels: u64,
+    /// Where the diff image was written, if one was.
+    pub diff_path: Option<PathBuf>,
+    /// Whether the baseline was overwritten rather than compared against.
+    pub baseline_updated: bool,
+}
+
+impl Comparison {
+    /// Returns true if no compared pixels differed.
+    pub fn is_match(&self) -> bool {
+        self.differing_pixels == 0
+    }
+
+    /// The fraction of compared pixels that differed.
+    pub fn diff_ratio(&self) -> f64 {
+        if self.compared_pixels == 0 {
+            0.0
+        } else {
+            self.differing_pixels as f64 / self.compared_pixels as f64
+        }
+    }
+}
+
+/// Compare the PNG-encoded image `actual` against the PNG baseline stored at `baseline`.
+///
+/// If `UPDATE_BASELINES_VAR` is set, the baseline is instead replaced with `actual`. Otherwise,
+/// a missing baseline is reported as `VisualError::MissingBaseline`.
+pub fn compare<P: AsRef<Path>>(
+    actual: &[u8],
+    baseline: P,
+    options: &Options,
+) -> Result<Comparison, VisualError> {
+    let update = ::std::env::var_os(UPDATE_BASELINES_VAR)
+        .map(|v| !v.is_empty() && v.to_str() != Some("0"))
+        .unwrap_or(false);
+    compare_inner(actual, baseline.as_ref(), options, update)
+}
+
+fn compare_inner(
+    actual: &[u8],
+    baseline: &Path,
+    options: &Options,
+    update: bool,
+) -> Result<Comparison, VisualError> {
+    let actual_img = Image::decode(actual)?;
+
+    if update {
+        if let Some(dir) = baseline.parent() {
+            fs::create_dir_all(dir).map_err(VisualError::Io)?;
+        }
+        fs::write(baseline, actual).map_err(VisualError::Io)?;
+        return Ok(Comparison {
+            actual_size: (actual_img.width, actual_img.height),
+            baseline_size: None,
+            compared_pixels: 0,
+            differing_pixels: 0,
+            diff_path: None,
+            baseline_updated: true,
+        });
+    }
+
+    let expected = match fs::read(baseline) {
+        Ok(expected) => expected,
+        Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => {
+            return Err(VisualError::MissingBaseline(baseline.to_path_buf()));
+        }
+        Err(e) => return Err(VisualError::Io(e)),
+    };
+    let expected = Image::decode(&expected)?;
+
+    let width = ::std::cmp::max(actual_img.width, expected.width);
+    let height = ::std::cmp::max(actual_img.height, expected.height);
+    let mut diff = Image::new(width, height);
+    let mut compared = 0;
+    let mut differing = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y as usize * width as usize + x as usize) * 4;
+            let a = pixel(&actual_img, x, y);
+            if options.ignore.iter().any(|r| r.contains(x, y)) {
+                diff.data[i..i + 4].copy_from_slice(&[0x40, 0x40, 0xc0, 0xff]);
+                continue;
+            }
+
+            compared += 1;
+            let same = match (a, pixel(&expected, x, y)) {
+                (Some(a), Some(e)) => a.iter().zip(e.iter()).all(|(a, e)| {
+                    (i16::from(*a) - i16::from(*e)).abs() <= i16::from(options.tolerance)
+                }),
+                _ => false,
+            };
+
+            if same {
+                // show matching pixels as a faded version of the actual image
+                let a = a.unwrap();
+                let luma = (u32::from(a[0]) * 3 + u32::from(a[1]) * 6 + u32::from(a[2])) / 10;
+                let faded = (0xff - (0xff - luma) / 4) as u8;
+                diff.data[i..i + 4].copy_from_slice(&[faded, faded, faded, 0xff]);
+            } else {
+                differing += 1;
+                diff.data[i..i + 4].copy_from_slice(&[0xff, 0x00, 0x00, 0xff]);
+            }
+        }
+    }
+
+    let diff_path = match options.diff_path {
+        Some(ref path) if differing != 0 => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(VisualError::Io)?;
+            }
+            fs::write(path, diff.encode()?).map_err(VisualError::Io)?;
+            Some(path.clone())
+        }
+        _ => None,
+    };
+
+    Ok(Comparison {
+        actual_size: (actual_img.width, actual_img.height),
+        baseline_size: Some((expected.width, expected.height)),
+        compared_pixels: compared,
+        differing_pixels: differing,
+        diff_path,
+        baseline_updated: false,
+    })
+}
+
+fn pixel(img: &Image, x: u32, y: u32) -> Option<&[u8]> {
+    if x >= img.width || y >= img.height {
+        return None;
+    }
+    let i = (y as usize * img.width as usize + x as usize) * 4;
+    Some(&img.data[i..i + 4])
+}
+
+impl From<ImageError> for VisualError {
+    fn from(e: ImageError) -> Self {
+        VisualError::Image(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, f: &Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        let mut img = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = (y as usize * width as usize + x as usize) * 4;
+                img.data[i..i + 4].copy_from_slice(&f(x, y));
+            }
+        }
+        img.encode().unwrap()
+    }
+
+    /// A directory of its own for each test, so that tests running at the same time (or in
+    /// other processes) do not overwrite each other's images.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(test: &str) -> Self {
+            let dir = ::std::env::temp_dir().join(format!(
+                "fantoccini-visual-{}-{}",
+                ::std::process::id(),
+                test
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn join(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn tolerance_and_ignore() {
+        let dir = TempDir::new("tolerance_and_ignore");
+        let baseline = dir.join("baseline.png");
+        fs::write(&baseline, png(4, 4, &|_, _| [100, 100, 100, 255])).unwrap();
+
+        // slightly off everywhere, and very off in the top-left corner
+        let actual = png(4, 4, &|x, y| {
+            if x == 0 && y == 0 {
+                [255, 0, 0, 255]
+            } else {
+                [102, 100, 98, 255]
+            }
+        });
+
+        let mut options = Options::default();
+        let r = compare_inner(&actual, &baseline, &options, false).unwrap();
+        assert_eq!(r.compared_pixels, 16);
+        assert_eq!(r.differing_pixels, 16);
+
+        options.tolerance = 2;
+        let r = compare_inner(&actual, &baseline, &options, false).unwrap();
+        assert_eq!(r.differing_pixels, 1);
+
+        options.ignore.push(Region {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        });
+        let r = compare_inner(&actual, &baseline, &options, false).unwrap();
+        assert_eq!(r.compared_pixels, 15);
+        assert!(r.is_match());
+    }
+
+    #[test]
+    fn size_mismatch_and_diff_image() {
+        let dir = TempDir::new("size_mismatch_and_diff_image");
+        let baseline = dir.join("baseline.png");
+        fs::write(&baseline, png(2, 2, &|_, _| [0, 0, 0, 255])).unwrap();
+
+        let mut options = Options::default();
+        options.diff_path = Some(dir.join("diff.png"));
+        let r = compare_inner(
+            &png(2, 3, &|_, _| [0, 0, 0, 255]),
+            &baseline,
+            &options,
+            false,
+        )
+        .unwrap();
+        assert_eq!(r.differing_pixels, 2);
+        assert_eq!(r.baseline_size, Some((2, 2)));
+
+        let diff = Image::decode(&fs::read(r.diff_path.unwrap()).unwrap()).unwrap();
+        assert_eq!((diff.width, diff.height), (2, 3));
+        assert_eq!(pixel(&diff, 1, 2), Some(&[0xff, 0, 0, 0xff][..]));
+    }
+
+    #[test]
+    fn update_mode() {
+        let dir = TempDir::new("update_mode");
+        let baseline = dir.join("nested/baseline.png");
+        let actual = png(1, 1, &|_, _| [1, 2, 3, 255]);
+
+        match compare_inner(&actual, &baseline, &Options::default(), false) {
+            Err(VisualError::MissingBaseline(ref p)) if p == &baseline => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        let r = compare_inner(&actual, &baseline, &Options::default(), true).unwrap();
+        assert!(r.baseline_updated);
+        let r = compare_inner(&actual, &baseline, &Options::default(), false).unwrap();
+        assert!(r.is_match());
+    }
+}